@@ -0,0 +1,215 @@
+//! Proving and verifying ACIR programs in memory, without going through a Noir package on disk.
+//!
+//! ```ignore
+//! let common_reference_string = halo2_kzg::common_reference_string(&program.bytecode)?;
+//! let prover = halo2_kzg::Prover::new(program, common_reference_string);
+//! let proof = prover.prove(&inputs, &mut halo2_kzg::NoOracles)?;
+//!
+//! let verifier = prover.verifier();
+//! assert!(verifier.verify(&proof.proof, proof.encoded_public_inputs.clone())?);
+//! ```
+
+use acvm::{
+    acir::{circuit::Circuit, native_types::WitnessMap},
+    FieldElement, ProofSystemCompiler,
+};
+use halo2_backend::Halo2;
+use nargo::artifacts::program::PreprocessedProgram;
+use noirc_abi::{input_parser::InputValue, InputMap};
+
+use crate::{
+    cli::{
+        execute::execute_circuit,
+        fs::common_reference_string::{
+            is_trusted_common_reference_string, load_common_reference_string,
+        },
+        oracle::OracleResolver,
+    },
+    errors::{CliError, ProofError},
+};
+
+/// Returns a common reference string large enough for `circuit`.
+///
+/// This reads and writes the cache shared with the command line interface, which holds one
+/// common reference string per degree in `~/.nargo/backends/halo2-kzg`, or in
+/// `$NARGO_BACKEND_CACHE_DIR/halo2-kzg` if set. If none of the cached ones fits, a new one is
+/// sampled locally and written to the cache, which is only suitable for testing. The cache is
+/// locked while in use, so this may block on other processes using it.
+pub fn common_reference_string(circuit: &Circuit) -> Result<Vec<u8>, ProofError> {
+    load_common_reference_string(circuit).map_err(|err| match err {
+        CliError::CommonReferenceStringError(err) => ProofError::CommonReferenceStringError(err),
+        err => ProofError::CommonReferenceStringCacheError(err.to_string()),
+    })
+}
+
+/// Whether `common_reference_string` is a cached one imported from a trusted setup.
+///
+/// This reads the provenance recorded in the cache described in [`common_reference_string`].
+pub fn is_trusted_setup(common_reference_string: &[u8]) -> bool {
+    is_trusted_common_reference_string(common_reference_string)
+}
+
+/// An oracle resolver which knows no oracles, for programs which do not call any.
+pub struct NoOracles;
+
+impl OracleResolver for NoOracles {
+    fn resolve(
+        &mut self,
+        _name: &str,
+        _inputs: &[FieldElement],
+    ) -> Option<Result<Vec<FieldElement>, String>> {
+        None
+    }
+}
+
+/// A proof along with the public inputs it was made for.
+#[derive(Debug, Clone)]
+pub struct Proof {
+    /// The serialized proof.
+    pub proof: Vec<u8>,
+    /// The public parameters of the program.
+    pub public_inputs: InputMap,
+    /// The value returned by the program, if any. It is part of the public inputs.
+    pub return_value: Option<InputValue>,
+    /// The public inputs and return value as the verifier expects them.
+    pub encoded_public_inputs: WitnessMap,
+}
+
+/// Proves a program for any number of inputs.
+pub struct Prover {
+    program: PreprocessedProgram,
+    common_reference_string: Vec<u8>,
+}
+
+impl Prover {
+    /// Creates a prover for a compiled `program`, proving with `common_reference_string`.
+    ///
+    /// The common reference string must support the size of the program, see
+    /// [`common_reference_string`].
+    pub fn new(program: PreprocessedProgram, common_reference_string: Vec<u8>) -> Self {
+        Self {
+            program,
+            common_reference_string,
+        }
+    }
+
+    /// The program this prover proves.
+    pub fn program(&self) -> &PreprocessedProgram {
+        &self.program
+    }
+
+    /// The common reference string this prover proves with, as given to [`Prover::new`].
+    pub fn common_reference_string(&self) -> &[u8] {
+        &self.common_reference_string
+    }
+
+    /// Solves the witness of the program for `inputs` and proves it.
+    ///
+    /// Oracle calls made by the program are resolved by `oracle_resolver`.
+    pub fn prove(
+        &self,
+        inputs: &InputMap,
+        oracle_resolver: &mut dyn OracleResolver,
+    ) -> Result<Proof, ProofError> {
+        let PreprocessedProgram {
+            abi,
+            bytecode,
+            proving_key,
+            ..
+        } = &self.program;
+
+        let initial_witness = abi.encode(inputs, None)?;
+        let solved_witness = execute_circuit(bytecode.clone(), initial_witness, oracle_resolver)?;
+
+        let public_abi = abi.clone().public_abi();
+        let (public_inputs, return_value) = public_abi.decode(&solved_witness)?;
+        let encoded_public_inputs = public_abi.encode(&public_inputs, return_value.clone())?;
+
+        let proof = Halo2
+            .prove_with_pk(
+                &self.common_reference_string,
+                bytecode,
+                solved_witness,
+                proving_key,
+                false,
+            )
+            .map_err(ProofError::ProofSystemCompilerError)?;
+
+        Ok(Proof {
+            proof,
+            public_inputs,
+            return_value,
+            encoded_public_inputs,
+        })
+    }
+
+    /// Returns a verifier for the proofs made by this prover.
+    pub fn verifier(&self) -> Verifier {
+        Verifier::from_program(&self.program, self.common_reference_string.clone())
+    }
+}
+
+/// Verifies proofs of a circuit.
+#[derive(Debug, Clone)]
+pub struct Verifier {
+    circuit: Circuit,
+    verification_key: Vec<u8>,
+    common_reference_string: Vec<u8>,
+}
+
+impl Verifier {
+    /// Creates a verifier from a circuit, its verification key and the common reference string
+    /// the proofs were made with.
+    ///
+    /// The halo2 verifier rebuilds the constraint system from the circuit, so the key alone is not enough.
+    pub fn new(
+        circuit: Circuit,
+        verification_key: Vec<u8>,
+        common_reference_string: Vec<u8>,
+    ) -> Self {
+        Self {
+            circuit,
+            verification_key,
+            common_reference_string,
+        }
+    }
+
+    /// Creates a verifier for the proofs of a compiled `program`, using its circuit and
+    /// verification key.
+    pub fn from_program(program: &PreprocessedProgram, common_reference_string: Vec<u8>) -> Self {
+        Self::new(
+            program.bytecode.clone(),
+            program.verification_key.clone(),
+            common_reference_string,
+        )
+    }
+
+    /// The circuit whose proofs this verifier checks.
+    pub fn circuit(&self) -> &Circuit {
+        &self.circuit
+    }
+
+    /// The serialized verification key of the circuit.
+    pub fn verification_key(&self) -> &[u8] {
+        &self.verification_key
+    }
+
+    /// The common reference string this verifier verifies with.
+    pub fn common_reference_string(&self) -> &[u8] {
+        &self.common_reference_string
+    }
+
+    /// Whether `proof` is valid for the encoded `public_inputs`.
+    pub fn verify(&self, proof: &[u8], public_inputs: WitnessMap) -> Result<bool, ProofError> {
+        Halo2
+            .verify_with_vk(
+                &self.common_reference_string,
+                proof,
+                public_inputs,
+                &self.circuit,
+                &self.verification_key,
+                false,
+            )
+            .map_err(ProofError::ProofSystemCompilerError)
+    }
+}
@@ -0,0 +1,225 @@
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use halo2_backend::Halo2;
+use nargo::artifacts::program::PreprocessedProgram;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use super::{
+    crs_cmd::TrustedSetupArgs,
+    fs::proof::{decode_public_inputs, ProofBundle},
+    report::Report,
+    verify_cmd::{check_proof, load_verifier},
+};
+use crate::{
+    constants::PROOF_EXT,
+    errors::{CliError, FilesystemError},
+    utils::read_program_from_file,
+    Verifier,
+};
+
+/// Formats in which the batch verification report can be printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum ReportFormat {
+    Table,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ProofStatus {
+    /// The proof verified.
+    Valid,
+    /// The proof was read but did not verify, or was made for another circuit or setup.
+    Invalid,
+    /// The proof could not be read.
+    Malformed,
+}
+
+#[derive(Debug, Serialize)]
+struct ProofReport {
+    path: PathBuf,
+    status: ProofStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct BatchReport {
+    valid: usize,
+    invalid: usize,
+    malformed: usize,
+    proofs: Vec<ProofReport>,
+}
+
+/// Verifies every proof in `batch` in parallel against the circuit at `circuit_build_path`,
+/// using the public inputs saved in each proof bundle, and prints a report.
+///
+/// With `--json` the report goes into the fields of the JSON result, whatever `report_format` is.
+///
+/// Returns an error if any proof is invalid or malformed.
+pub(crate) fn verify_batch(
+    batch: &[PathBuf],
+    circuit_build_path: PathBuf,
+    trusted_setup: &TrustedSetupArgs,
+    report_format: ReportFormat,
+    result: &mut Report,
+) -> Result<(), CliError<Halo2>> {
+    let proof_paths = batch_proof_paths(batch)?;
+
+    let PreprocessedProgram {
+        bytecode,
+        verification_key,
+        ..
+    } = read_program_from_file(circuit_build_path)?;
+
+    let verifier = load_verifier(bytecode, verification_key, trusted_setup)?;
+
+    let proofs: Vec<ProofReport> = proof_paths
+        .into_par_iter()
+        .map(|proof_path| {
+            let result = check_batch_proof(&proof_path, &verifier);
+            let (status, error) = match result {
+                Ok(()) => (ProofStatus::Valid, None),
+                Err(
+                    err @ (CliError::FilesystemError(_) | CliError::UnsupportedProofBundle(..)),
+                ) => (ProofStatus::Malformed, Some(err.to_string())),
+                Err(err) => (ProofStatus::Invalid, Some(err.to_string())),
+            };
+
+            ProofReport {
+                path: proof_path,
+                status,
+                error,
+            }
+        })
+        .collect();
+
+    let count = |status| proofs.iter().filter(|proof| proof.status == status).count();
+    let report = BatchReport {
+        valid: count(ProofStatus::Valid),
+        invalid: count(ProofStatus::Invalid),
+        malformed: count(ProofStatus::Malformed),
+        proofs,
+    };
+
+    let failed = report.invalid + report.malformed;
+    let total = report.proofs.len();
+
+    if result.is_json() {
+        result.field("valid", report.valid);
+        result.field("invalid", report.invalid);
+        result.field("malformed", report.malformed);
+        result.field("proofs", report.proofs);
+    } else {
+        match report_format {
+            ReportFormat::Table => print_table(&report),
+            ReportFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(&report).expect("infallible: serializing to JSON")
+            ),
+        }
+    }
+
+    if failed > 0 {
+        return Err(CliError::BatchVerificationFailed(failed, total));
+    }
+    Ok(())
+}
+
+/// Verifies the proof bundle at `proof_path` against the public inputs saved in it.
+fn check_batch_proof(proof_path: &Path, verifier: &Verifier) -> Result<(), CliError<Halo2>> {
+    let proof_bundle = ProofBundle::read(proof_path)?;
+    if !proof_bundle.has_fingerprints() {
+        return Err(FilesystemError::PublicInputsNotValid(
+            proof_path.to_path_buf(),
+            "the proof predates proof bundles and holds no public inputs".to_owned(),
+        )
+        .into());
+    }
+
+    let public_inputs = decode_public_inputs(&proof_bundle.public_inputs, verifier.circuit())
+        .map_err(|reason| {
+            FilesystemError::PublicInputsNotValid(proof_path.to_path_buf(), reason)
+        })?;
+
+    check_proof(
+        proof_path.to_path_buf(),
+        &proof_bundle,
+        verifier,
+        public_inputs,
+    )
+}
+
+fn print_table(report: &BatchReport) {
+    let path_width = report
+        .proofs
+        .iter()
+        .map(|proof| proof.path.display().to_string().len())
+        .max()
+        .unwrap_or_default()
+        .max("PROOF".len());
+
+    println!("{:<9}  {:<path_width$}  ERROR", "STATUS", "PROOF");
+    for proof in &report.proofs {
+        let status = match proof.status {
+            ProofStatus::Valid => "valid",
+            ProofStatus::Invalid => "invalid",
+            ProofStatus::Malformed => "malformed",
+        };
+        // Some errors span several lines, which would break the table.
+        let error = proof
+            .error
+            .as_deref()
+            .unwrap_or_default()
+            .replace('\n', " ");
+        println!(
+            "{status:<9}  {:<path_width$}  {error}",
+            proof.path.display()
+        );
+    }
+
+    println!();
+    println!(
+        "{} valid, {} invalid, {} malformed out of {} proofs",
+        report.valid,
+        report.invalid,
+        report.malformed,
+        report.proofs.len()
+    );
+}
+
+/// Returns the proof files in the directories of `batch`, along with the files listed directly.
+fn batch_proof_paths(batch: &[PathBuf]) -> Result<Vec<PathBuf>, FilesystemError> {
+    let mut proof_paths = Vec::new();
+    for path in batch {
+        if path.is_dir() {
+            let mut dir_proof_paths: Vec<PathBuf> = std::fs::read_dir(path)
+                .map_err(|err| FilesystemError::read_failed(path, err))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| is_proof_file(path))
+                .collect();
+            dir_proof_paths.sort();
+            proof_paths.extend(dir_proof_paths);
+        } else {
+            proof_paths.push(path.clone());
+        }
+    }
+
+    if proof_paths.is_empty() {
+        let batch: Vec<_> = batch
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        return Err(FilesystemError::InvalidBatch(
+            batch.join(" "),
+            "no proof files found".to_owned(),
+        ));
+    }
+    Ok(proof_paths)
+}
+
+fn is_proof_file(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some(PROOF_EXT)
+}
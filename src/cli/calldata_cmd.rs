@@ -0,0 +1,126 @@
+use acvm::acir::native_types::WitnessMap;
+use clap::{Args, ValueEnum};
+use halo2_backend::Halo2;
+use nargo::artifacts::program::PreprocessedProgram;
+
+use super::{fs::proof::ProofBundle, report::Report, verify_cmd::read_verifier_inputs};
+use crate::{
+    constants::{PROOFS_DIR, PROOF_EXT, TARGET_DIR, VERIFIER_INPUT_FILE},
+    errors::CliError,
+    utils::{read_program_from_file, InputFormat},
+    Halo2Config,
+};
+
+/// Encodes a proof and its public inputs as calldata for the Solidity verifier made by `contract`
+#[derive(Debug, Clone, Args)]
+pub(crate) struct CalldataCommand {
+    /// The proof to encode
+    proof: String,
+
+    /// The name of the circuit build files (ACIR, proving and verification keys)
+    circuit_name: String,
+
+    /// The name of the file (TOML or JSON) which contains the inputs for the verifier
+    #[clap(long, short, default_value = VERIFIER_INPUT_FILE)]
+    verifier_name: String,
+
+    /// The format of the input files, detected from their extension or which file exists if not given
+    #[clap(long, value_enum)]
+    input_format: Option<InputFormat>,
+
+    /// How to print the calldata
+    #[clap(long, value_enum, default_value_t = CalldataFormat::Hex)]
+    format: CalldataFormat,
+
+    /// The address of the deployed verifier, for `--format cast`
+    #[clap(long, default_value = "$VERIFIER")]
+    address: String,
+}
+
+/// Ways in which calldata can be printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CalldataFormat {
+    /// The hex encoded calldata
+    Hex,
+    /// A `cast call` command calling the verifier with the calldata
+    Cast,
+}
+
+pub(crate) fn run(
+    args: CalldataCommand,
+    config: Halo2Config,
+    report: &mut Report,
+) -> Result<(), CliError<Halo2>> {
+    let proof_path = config
+        .program_dir
+        .join(PROOFS_DIR)
+        .join(&args.proof)
+        .with_extension(PROOF_EXT);
+    let circuit_build_path = config.program_dir.join(TARGET_DIR).join(&args.circuit_name);
+
+    let PreprocessedProgram { abi, .. } = read_program_from_file(circuit_build_path)?;
+    let public_inputs = read_verifier_inputs(
+        &config.program_dir,
+        &args.verifier_name,
+        args.input_format,
+        abi,
+    )?;
+    let proof = ProofBundle::read(&proof_path)?.proof()?;
+
+    let calldata = format!("0x{}", hex::encode(encode_calldata(&proof, &public_inputs)));
+
+    report.field("calldata", &calldata);
+    match args.format {
+        CalldataFormat::Hex => report.message(&calldata),
+        CalldataFormat::Cast => report.message(format!("cast call {} {calldata}", args.address)),
+    }
+    Ok(())
+}
+
+/// Encodes `proof` and its `public_inputs` as calldata for the verifier made by
+/// `eth_contract_from_vk`.
+///
+/// The verifier takes no function selector: the calldata is each public input as a 32 byte
+/// big-endian word, in the order of their witnesses, followed by the proof.
+pub(crate) fn encode_calldata(proof: &[u8], public_inputs: &WitnessMap) -> Vec<u8> {
+    let mut calldata: Vec<u8> = public_inputs
+        .clone()
+        .into_iter()
+        .flat_map(|(_, value)| value.to_be_bytes())
+        .collect();
+    calldata.extend_from_slice(proof);
+    calldata
+}
+
+#[cfg(test)]
+mod tests {
+    use acvm::{
+        acir::native_types::{Witness, WitnessMap},
+        FieldElement,
+    };
+
+    use super::encode_calldata;
+
+    #[test]
+    fn encodes_public_inputs_in_witness_order_before_the_proof() {
+        let mut public_inputs = WitnessMap::new();
+        public_inputs.insert(Witness(3), FieldElement::from(0x0102u128));
+        public_inputs.insert(Witness(1), -FieldElement::one());
+
+        let calldata = encode_calldata(&[0xaa, 0xbb], &public_inputs);
+
+        let expected = hex::decode(concat!(
+            // The field modulus minus one, for witness 1
+            "30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000000",
+            "0000000000000000000000000000000000000000000000000000000000000102",
+            "aabb",
+        ))
+        .unwrap();
+        assert_eq!(calldata, expected);
+    }
+
+    #[test]
+    fn encodes_only_the_proof_without_public_inputs() {
+        assert_eq!(encode_calldata(&[0xaa], &WitnessMap::new()), vec![0xaa]);
+    }
+}
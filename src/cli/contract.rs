@@ -1,32 +1,202 @@
-use super::fs::common_reference_string::{
-    read_cached_common_reference_string, update_common_reference_string,
-    write_cached_common_reference_string,
-};
-use crate::constants::CONTRACT_DIR;
+use std::path::{Path, PathBuf};
+
+use super::calldata_cmd::encode_calldata;
+use super::crs_cmd::TrustedSetupArgs;
+use super::evm::{compile_solidity, LocalEvm, EIP_170_CODE_SIZE_LIMIT};
+use super::foundry::scaffold_foundry_project;
+use super::fs::proof::{decode_public_inputs, ProofBundle};
+use super::inputs::InputArgs;
+use super::oracle::OracleArgs;
+use super::outputs::{ProofOutputArgs, WrittenProof};
+use super::prove_cmd::{prove_with_path, ProveContext};
+use super::report::Report;
+use crate::constants::{CONTRACT_DIR, CONTRACT_EXT, PROGRAM_EXT, PROOFS_DIR, PROOF_EXT};
+use crate::errors::FilesystemError;
 use crate::utils::{create_named_dir, read_program_from_file, write_to_file};
-use crate::Halo2Config;
 use crate::{constants::TARGET_DIR, errors::CliError};
-use acvm::SmartContract;
+use crate::{Halo2Config, Verifier};
+use acvm::{acir::native_types::WitnessMap, SmartContract};
 use clap::Args;
 use halo2_backend::Halo2;
+use serde::Serialize;
 
 /// Generates a Solidity verifier smart contract for the program
 #[derive(Debug, Clone, Args)]
 pub(crate) struct ContractCommand {
     /// The name of the circuit build files (ACIR, proving and verification keys)
-    circuit_name: String,
+    #[clap(required_unless_present = "all")]
+    circuit_name: Option<String>,
+
+    /// Write the contract to this file instead of `contract/<circuit_name>.sol`
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+
+    /// The name of the verifier contract, defaults to the circuit name in PascalCase followed by `Verifier`
+    #[clap(long, value_parser = parse_contract_name)]
+    contract_name: Option<String>,
+
+    /// Generate a contract for every circuit in `target/`
+    #[clap(long, conflicts_with_all = ["circuit_name", "output", "contract_name"])]
+    all: bool,
+
+    #[clap(flatten)]
+    trusted_setup: TrustedSetupArgs,
+
+    /// Report the deployed bytecode size of the contract and its verification gas, measured in a local EVM
+    #[clap(long = "report")]
+    evm_report: bool,
+
+    /// The proof to measure verification gas with, defaults to `proofs/<circuit_name>.proof`
+    #[clap(long, requires = "evm_report", conflicts_with = "all")]
+    sample_proof: Option<String>,
+
+    /// The Solidity compiler used to compile the contract for `--report`
+    #[clap(long, default_value = "solc")]
+    solc: PathBuf,
+
+    /// Fail if the contract exceeds the EIP-170 bytecode size limit
+    #[clap(long, requires = "evm_report")]
+    strict: bool,
+
+    /// Also write a Foundry project around the contract, with a test verifying a proof of the
+    /// prover inputs and a deploy script
+    #[clap(long, conflicts_with = "all")]
+    foundry: bool,
+
+    #[clap(flatten)]
+    inputs: InputArgs,
+
+    #[clap(flatten)]
+    oracle: OracleArgs,
+}
+
+/// The size and cost of a verifier contract, as measured by `contract --report`.
+#[derive(Debug, Serialize)]
+struct ContractReport {
+    contract_name: String,
+    /// The size of the deployed bytecode in bytes.
+    code_size: usize,
+    exceeds_eip_170: bool,
+    deployment_gas: u64,
+    /// The gas used to verify the sample proof, if there is one and the contract accepts it.
+    verification_gas: Option<u64>,
 }
 
-pub(crate) fn run(args: ContractCommand, config: Halo2Config) -> Result<(), CliError<Halo2>> {
-    let circuit_build_path = config.program_dir.join(TARGET_DIR).join(args.circuit_name);
+pub(crate) fn run(
+    args: ContractCommand,
+    config: Halo2Config,
+    report: &mut Report,
+) -> Result<(), CliError<Halo2>> {
+    let target_dir = config.program_dir.join(TARGET_DIR);
+    let contract_dir = config.program_dir.join(CONTRACT_DIR);
+    let proof_dir = config.program_dir.join(PROOFS_DIR);
+    let mut oracle_resolver = args.oracle.resolvers();
+
+    let circuit_names = match args.circuit_name {
+        Some(circuit_name) => vec![circuit_name],
+        None => circuit_names(&target_dir)?,
+    };
+
+    let mut contract_reports = Vec::new();
+    for circuit_name in circuit_names {
+        let contract_path = args.output.clone().unwrap_or_else(|| {
+            contract_dir
+                .join(&circuit_name)
+                .with_extension(CONTRACT_EXT)
+        });
+        let contract_name = args
+            .contract_name
+            .clone()
+            .unwrap_or_else(|| default_contract_name(&circuit_name));
 
-    let common_reference_string = read_cached_common_reference_string();
+        let (smart_contract_string, verifier) = generate_contract(
+            &target_dir.join(&circuit_name),
+            &contract_path,
+            &contract_name,
+            &args.trusted_setup,
+        )?;
 
+        report.path_written(&contract_path);
+        report.message(format!(
+            "Contract {contract_name} successfully created and located at {}",
+            contract_path.display()
+        ));
+
+        if args.evm_report {
+            let sample_proof_path = proof_dir
+                .join(args.sample_proof.as_deref().unwrap_or(&circuit_name))
+                .with_extension(PROOF_EXT);
+            let contract_report = measure_contract(
+                &args.solc,
+                contract_name.clone(),
+                &smart_contract_string,
+                &verifier,
+                &sample_proof_path,
+            )?;
+            print_contract_report(&contract_report, report);
+            contract_reports.push(contract_report);
+        }
+
+        if args.foundry {
+            let context = ProveContext::new(
+                config.program_dir.clone(),
+                &circuit_name,
+                &args.trusted_setup,
+                &mut oracle_resolver,
+            );
+            let paths_written = scaffold_foundry(
+                context,
+                &circuit_name,
+                &contract_path,
+                &contract_name,
+                &verifier,
+                &args.inputs,
+            )?;
+            for path in &paths_written {
+                report.path_written(path);
+            }
+            report.message(format!(
+                "Foundry project created in {}, run `forge test` there to check the verifier",
+                foundry_project_dir(&contract_path).display()
+            ));
+        }
+    }
+
+    if !args.evm_report {
+        return Ok(());
+    }
+
+    let oversized: Vec<&str> = contract_reports
+        .iter()
+        .filter(|contract_report| contract_report.exceeds_eip_170)
+        .map(|contract_report| contract_report.contract_name.as_str())
+        .collect();
+    let oversized = oversized.join(", ");
+    report.field("contracts", &contract_reports);
+
+    if args.strict && !oversized.is_empty() {
+        return Err(CliError::ContractTooLarge(
+            oversized,
+            EIP_170_CODE_SIZE_LIMIT,
+        ));
+    }
+    Ok(())
+}
+
+/// Generates the verifier contract named `contract_name` for the circuit at `circuit_build_path`
+/// and writes it to `contract_path`.
+///
+/// Returns the source of the contract along with the verifier it mirrors.
+fn generate_contract(
+    circuit_build_path: &Path,
+    contract_path: &Path,
+    contract_name: &str,
+    trusted_setup: &TrustedSetupArgs,
+) -> Result<(String, Verifier), CliError<Halo2>> {
     let preprocessed_program = read_program_from_file(circuit_build_path)?;
 
     let common_reference_string =
-        update_common_reference_string(&common_reference_string, &preprocessed_program.bytecode)
-            .map_err(CliError::CommonReferenceStringError)?;
+        trusted_setup.load_common_reference_string(&preprocessed_program.bytecode)?;
 
     let smart_contract_string = Halo2
         .eth_contract_from_vk(
@@ -34,14 +204,293 @@ pub(crate) fn run(args: ContractCommand, config: Halo2Config) -> Result<(), CliE
             &preprocessed_program.verification_key,
         )
         .map_err(CliError::SmartContractError)?;
+    let smart_contract_string = rename_contract(&smart_contract_string, contract_name);
 
-    write_cached_common_reference_string(&common_reference_string);
+    if let Some(contract_dir) = contract_path.parent() {
+        if !contract_dir.as_os_str().is_empty() {
+            create_named_dir(contract_dir)?;
+        }
+    }
+    write_to_file(smart_contract_string.as_bytes(), contract_path)?;
 
-    let contract_dir = config.program_dir.join(CONTRACT_DIR);
-    create_named_dir(&contract_dir, "contract");
-    let contract_path = contract_dir.join("plonk_vk").with_extension("sol");
+    let verifier = Verifier::from_program(&preprocessed_program, common_reference_string);
+    Ok((smart_contract_string, verifier))
+}
 
-    let path = write_to_file(smart_contract_string.as_bytes(), &contract_path);
-    println!("Contract successfully created and located at {path}");
-    Ok(())
+/// Deploys the verifier contract into a local EVM to measure its size and the gas it uses to
+/// verify the proof at `sample_proof_path`, if any.
+fn measure_contract(
+    solc: &Path,
+    contract_name: String,
+    smart_contract_string: &str,
+    verifier: &Verifier,
+    sample_proof_path: &Path,
+) -> Result<ContractReport, CliError<Halo2>> {
+    let bytecode = compile_solidity(solc, smart_contract_string, &contract_name)?;
+
+    let mut evm = LocalEvm::new();
+    let deployment = evm.deploy(bytecode)?;
+
+    let verification_gas = match read_sample_proof(sample_proof_path, verifier)
+        .ok()
+        .flatten()
+    {
+        Some((proof, public_inputs)) => {
+            let verification =
+                evm.call(deployment.address, encode_calldata(&proof, &public_inputs))?;
+            if !verification.valid {
+                eprintln!(
+                    "Warning: {contract_name} rejects the sample proof {}",
+                    sample_proof_path.display()
+                );
+            }
+            verification.valid.then_some(verification.gas_used)
+        }
+        None => {
+            eprintln!(
+                "Warning: no sample proof with public inputs at {}, run `halo2_kzg prove` to measure verification gas",
+                sample_proof_path.display()
+            );
+            None
+        }
+    };
+
+    Ok(ContractReport {
+        contract_name,
+        code_size: deployment.code_size,
+        exceeds_eip_170: deployment.code_size > EIP_170_CODE_SIZE_LIMIT,
+        deployment_gas: deployment.gas_used,
+        verification_gas,
+    })
+}
+
+/// Proves the prover inputs to `proofs/<circuit_name>.proof` and writes a Foundry project testing
+/// the verifier at `contract_path` with that proof.
+fn scaffold_foundry(
+    context: ProveContext,
+    circuit_name: &str,
+    contract_path: &Path,
+    contract_name: &str,
+    verifier: &Verifier,
+    inputs: &InputArgs,
+) -> Result<Vec<PathBuf>, CliError<Halo2>> {
+    let output = ProofOutputArgs::named(circuit_name, circuit_name);
+    let written_proof = prove_with_path(&output, inputs, context)?;
+    let WrittenProof::Saved(proof_path) = written_proof else {
+        unreachable!("named proofs are saved to the proofs directory")
+    };
+
+    let (proof, public_inputs) = read_sample_proof(&proof_path, verifier)?
+        .ok_or_else(|| CliError::InvalidProof(proof_path.clone()))?;
+
+    let contract_file = contract_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .expect("contract paths end in a file name");
+    let mut paths_written = scaffold_foundry_project(
+        &foundry_project_dir(contract_path),
+        contract_file,
+        contract_name,
+        &encode_calldata(&proof, &public_inputs),
+    )?;
+    paths_written.push(proof_path);
+    Ok(paths_written)
+}
+
+/// The Foundry project wraps the directory holding the contract.
+fn foundry_project_dir(contract_path: &Path) -> PathBuf {
+    match contract_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Reads the proof bundle at `proof_path` along with the public inputs saved in it.
+///
+/// Returns `None` if the proof cannot be decoded or lacks its public inputs.
+fn read_sample_proof(
+    proof_path: &Path,
+    verifier: &Verifier,
+) -> Result<Option<(Vec<u8>, WitnessMap)>, FilesystemError> {
+    let proof_bundle = ProofBundle::read(proof_path)?;
+    if !proof_bundle.has_fingerprints() {
+        return Ok(None);
+    }
+
+    let Ok(public_inputs) = decode_public_inputs(&proof_bundle.public_inputs, verifier.circuit())
+    else {
+        return Ok(None);
+    };
+    Ok(proof_bundle
+        .proof()
+        .ok()
+        .map(|proof| (proof, public_inputs)))
+}
+
+fn print_contract_report(contract_report: &ContractReport, report: &Report) {
+    report.message(format!(
+        "Deployed bytecode size: {} bytes (EIP-170 limit: {EIP_170_CODE_SIZE_LIMIT} bytes)",
+        contract_report.code_size
+    ));
+    report.message(format!(
+        "Deployment gas: {}",
+        contract_report.deployment_gas
+    ));
+    if let Some(verification_gas) = contract_report.verification_gas {
+        report.message(format!("Verification gas: {verification_gas}"));
+    }
+
+    if contract_report.exceeds_eip_170 {
+        eprintln!(
+            "Warning: {} is {} bytes, over the EIP-170 limit of {EIP_170_CODE_SIZE_LIMIT} bytes, and cannot be deployed to Ethereum mainnet",
+            contract_report.contract_name, contract_report.code_size
+        );
+    }
+}
+
+/// Returns the names of the compiled programs in `target_dir`, sorted.
+fn circuit_names(target_dir: &Path) -> Result<Vec<String>, FilesystemError> {
+    let mut circuit_names: Vec<String> = std::fs::read_dir(target_dir)
+        .map_err(|err| FilesystemError::read_failed(target_dir, err))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some(PROGRAM_EXT))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_owned()))
+        .collect();
+    circuit_names.sort();
+
+    if circuit_names.is_empty() {
+        return Err(FilesystemError::PathNotValid(target_dir.to_path_buf()));
+    }
+    Ok(circuit_names)
+}
+
+/// Accepts `contract_name` if it is a Solidity identifier, as it is spliced into the source as is.
+fn parse_contract_name(contract_name: &str) -> Result<String, String> {
+    let mut chars = contract_name.chars();
+    let is_identifier = chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || matches!(first, '_' | '$'))
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '$'));
+
+    if !is_identifier {
+        return Err(format!("`{contract_name}` is not a Solidity identifier"));
+    }
+    Ok(contract_name.to_owned())
+}
+
+/// Returns the default name of the verifier contract of `circuit_name`, e.g. `MainVerifier` for
+/// `main` or `MerkleProofVerifier` for `merkle_proof`.
+fn default_contract_name(circuit_name: &str) -> String {
+    let mut contract_name: String = circuit_name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect();
+
+    // Solidity identifiers cannot start with a digit.
+    if contract_name.starts_with(|c: char| c.is_ascii_digit()) {
+        contract_name.insert(0, 'C');
+    }
+    contract_name + "Verifier"
+}
+
+/// Renames the first contract declared in the Solidity `source` to `contract_name`, leaving any
+/// libraries and interfaces as they are.
+pub(super) fn rename_contract(source: &str, contract_name: &str) -> String {
+    let mut renamed = false;
+    let lines: Vec<String> = source
+        .lines()
+        .map(|line| {
+            let declaration = line
+                .trim_start()
+                .strip_prefix("contract ")
+                .or_else(|| line.trim_start().strip_prefix("abstract contract "));
+            match declaration {
+                Some(declaration) if !renamed => {
+                    renamed = true;
+                    let old_name_len = declaration
+                        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$'))
+                        .unwrap_or(declaration.len());
+                    let prefix_len = line.len() - declaration.len();
+                    format!(
+                        "{}{contract_name}{}",
+                        &line[..prefix_len],
+                        &declaration[old_name_len..]
+                    )
+                }
+                _ => line.to_owned(),
+            }
+        })
+        .collect();
+
+    let mut renamed_source = lines.join("\n");
+    if source.ends_with('\n') {
+        renamed_source.push('\n');
+    }
+    renamed_source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{default_contract_name, parse_contract_name, rename_contract};
+
+    #[test]
+    fn accepts_only_solidity_identifiers_as_contract_names() {
+        for contract_name in ["Verifier", "_Verifier", "$verifier", "Verifier_2"] {
+            assert_eq!(
+                parse_contract_name(contract_name).as_deref(),
+                Ok(contract_name)
+            );
+        }
+        for contract_name in ["", "2Verifier", "My Verifier", "Verifier {}", "Vérifier"] {
+            assert!(parse_contract_name(contract_name).is_err());
+        }
+    }
+
+    #[test]
+    fn names_contracts_after_the_circuit() {
+        assert_eq!(default_contract_name("main"), "MainVerifier");
+        assert_eq!(default_contract_name("my_circuit"), "MyCircuitVerifier");
+        assert_eq!(
+            default_contract_name("my-circuit.v2"),
+            "MyCircuitV2Verifier"
+        );
+        assert_eq!(default_contract_name("2fa"), "C2faVerifier");
+    }
+
+    #[test]
+    fn renames_only_the_first_contract() {
+        let source = "\
+pragma solidity ^0.8.0;
+
+library Pairing {}
+
+interface IVerifier {}
+
+contract Halo2Verifier is IVerifier {
+    function verify() public {}
+}
+
+contract Other {}
+";
+
+        assert_eq!(
+            rename_contract(source, "MainVerifier"),
+            source.replace("contract Halo2Verifier ", "contract MainVerifier ")
+        );
+    }
+
+    #[test]
+    fn renames_abstract_contracts() {
+        assert_eq!(
+            rename_contract("abstract contract Base {}", "MainVerifier"),
+            "abstract contract MainVerifier {}"
+        );
+    }
 }
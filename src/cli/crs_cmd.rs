@@ -0,0 +1,290 @@
+use std::path::PathBuf;
+
+use acvm::acir::circuit::Circuit;
+use clap::{Args, Subcommand, ValueEnum};
+use halo2_backend::Halo2;
+
+use super::fs::{
+    common_reference_string::{
+        cached_common_reference_string_degrees, clear_cached_common_reference_strings,
+        common_reference_string_degree, common_reference_string_dir,
+        common_reference_string_location, downsize_common_reference_string,
+        generate_common_reference_string_for_degree, is_trusted_common_reference_string,
+        load_common_reference_string, read_cached_common_reference_string,
+        read_cached_common_reference_string_for_degree, read_common_reference_string_provenance,
+        write_cached_common_reference_string, write_imported_common_reference_string,
+        CommonReferenceStringSource,
+    },
+    powers_of_tau::common_reference_string_from_ptau,
+};
+use super::report::Report;
+use crate::errors::{CliError, FilesystemError};
+use crate::utils::{sha256_hex, write_to_file};
+
+/// The degrees a common reference string can be generated, exported or imported for, up to that
+/// of the largest published trusted setups.
+const DEGREES: std::ops::RangeInclusive<i64> = 1..=28;
+
+/// Manages the cached common reference strings
+#[derive(Debug, Clone, Args)]
+pub(crate) struct CrsCommand {
+    #[command(subcommand)]
+    command: CrsSubcommand,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum CrsSubcommand {
+    /// Shows the location, size, supported degree and hash of each cached common reference string
+    Show,
+    /// Makes sure a common reference string supporting circuits of degree `k` is cached
+    Ensure {
+        /// The circuit degree to support, i.e. circuits with up to 2^k rows
+        #[arg(long, short, value_parser = clap::value_parser!(u32).range(DEGREES))]
+        k: u32,
+    },
+    /// Deletes all cached common reference strings
+    Clear,
+    /// Copies a cached common reference string to a file
+    Export {
+        /// The file to write the common reference string to
+        path: PathBuf,
+
+        /// The degree of the common reference string to export, defaults to the largest cached one
+        #[arg(long, short, value_parser = clap::value_parser!(u32).range(DEGREES))]
+        k: Option<u32>,
+    },
+    /// Replaces the cached common reference string with a trusted setup read from a file
+    Import {
+        /// The file containing the trusted setup
+        path: PathBuf,
+
+        /// The format of the trusted setup file
+        #[arg(long, value_enum, default_value_t = SetupFormat::Halo2)]
+        format: SetupFormat,
+
+        /// Truncate the trusted setup to support circuits of degree `k` only
+        #[arg(long, short, value_parser = clap::value_parser!(u32).range(DEGREES))]
+        k: Option<u32>,
+    },
+}
+
+/// Formats of trusted setup files which can be imported
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SetupFormat {
+    /// Serialized halo2 KZG parameters, as published by the PSE perpetual powers of tau
+    Halo2,
+    /// A snarkjs `.ptau` ceremony transcript
+    Ptau,
+}
+
+/// Selects whether commands using the cached common reference string require a trusted setup
+#[derive(Debug, Clone, Default, Args)]
+pub(crate) struct TrustedSetupArgs {
+    /// Refuse to proceed unless the common reference string is the output of a trusted setup
+    #[clap(long)]
+    pub(crate) require_trusted_setup: bool,
+}
+
+impl TrustedSetupArgs {
+    /// Loads a common reference string for `circuit` from the cache, warning if it was not
+    /// imported from a trusted setup.
+    ///
+    /// With `--require-trusted-setup` it must have been imported from a trusted setup.
+    pub(crate) fn load_common_reference_string(
+        &self,
+        circuit: &Circuit,
+    ) -> Result<Vec<u8>, CliError<Halo2>> {
+        let common_reference_string = load_common_reference_string(circuit)?;
+
+        if !is_trusted_common_reference_string(&common_reference_string) {
+            if self.require_trusted_setup {
+                return Err(CliError::UntrustedCommonReferenceString);
+            }
+            eprintln!("{UNTRUSTED_WARNING}");
+        }
+
+        Ok(common_reference_string)
+    }
+}
+
+const UNTRUSTED_WARNING: &str = "Warning: the common reference string was NOT imported from a trusted setup, so proofs and verifier contracts relying on it are insecure. Import a trusted setup with `halo2_kzg crs import` for production use.";
+
+pub(crate) fn run(args: CrsCommand, report: &mut Report) -> Result<(), CliError<Halo2>> {
+    match args.command {
+        CrsSubcommand::Show => show(report),
+        CrsSubcommand::Ensure { k } => ensure(k, report),
+        CrsSubcommand::Clear => clear(report),
+        CrsSubcommand::Export { path, k } => export(path, k, report),
+        CrsSubcommand::Import { path, format, k } => import(path, format, k, report),
+    }
+}
+
+fn show(report: &mut Report) -> Result<(), CliError<Halo2>> {
+    let degrees = cached_common_reference_string_degrees();
+
+    if degrees.is_empty() {
+        return Err(
+            FilesystemError::MissingCommonReferenceString(common_reference_string_dir()?).into(),
+        );
+    }
+
+    let mut cached = Vec::new();
+    for k in degrees {
+        let crs_path = common_reference_string_location(k)?;
+        let common_reference_string = read_cached_common_reference_string(k)?
+            .ok_or_else(|| FilesystemError::CommonReferenceStringNotValid(crs_path.clone()))?;
+
+        let k = common_reference_string_degree(&common_reference_string)
+            .ok_or_else(|| FilesystemError::CommonReferenceStringNotValid(crs_path.clone()))?;
+        let sha256 = sha256_hex(&common_reference_string);
+
+        report.message(format!("Location: {}", crs_path.display()));
+        report.message(format!("Size: {} bytes", common_reference_string.len()));
+        report.message(format!(
+            "Degree: k = {k} (circuits with up to {} rows)",
+            1u64 << k
+        ));
+        report.message(format!("SHA-256: {sha256}"));
+
+        let (source, source_hash) = match read_common_reference_string_provenance(k) {
+            Some(provenance) if provenance.describes(&common_reference_string) => {
+                match provenance.source {
+                    CommonReferenceStringSource::Generated => {
+                        report.message("Source: generated locally (not a trusted setup)");
+                        ("generated", None)
+                    }
+                    CommonReferenceStringSource::Imported => {
+                        report.message(format!(
+                            "Source: imported from trusted setup with SHA-256 {}",
+                            provenance.source_hash.as_deref().unwrap_or_default()
+                        ));
+                        ("imported", provenance.source_hash)
+                    }
+                }
+            }
+            _ => {
+                report.message("Source: unknown (not a trusted setup)");
+                ("unknown", None)
+            }
+        };
+        report.message("");
+
+        cached.push(serde_json::json!({
+            "path": crs_path,
+            "size": common_reference_string.len(),
+            "k": k,
+            "sha256": sha256,
+            "source": source,
+            "source_sha256": source_hash,
+        }));
+    }
+    report.field("common_reference_strings", cached);
+
+    Ok(())
+}
+
+fn ensure(k: u32, report: &mut Report) -> Result<(), CliError<Halo2>> {
+    let crs_path = common_reference_string_location(k)?;
+    report.field("k", k);
+    report.field("path", &crs_path);
+
+    if read_cached_common_reference_string_for_degree(k)?.is_some() {
+        report.field("generated", false);
+        report.message(format!(
+            "Common reference string for k = {k} cached at {}",
+            crs_path.display()
+        ));
+        return Ok(());
+    }
+
+    let common_reference_string = generate_common_reference_string_for_degree(k);
+    write_cached_common_reference_string(&common_reference_string)?;
+    eprintln!("{UNTRUSTED_WARNING}");
+
+    report.field("generated", true);
+    report.path_written(&crs_path);
+    report.message(format!(
+        "Common reference string for k = {k} generated and cached at {}",
+        crs_path.display()
+    ));
+    Ok(())
+}
+
+fn clear(report: &mut Report) -> Result<(), CliError<Halo2>> {
+    let crs_dir = common_reference_string_dir()?;
+
+    let removed = clear_cached_common_reference_strings()?;
+
+    report.field("removed", removed);
+    report.message(format!(
+        "Removed {removed} cached common reference string(s) from {}",
+        crs_dir.display()
+    ));
+    Ok(())
+}
+
+fn export(path: PathBuf, k: Option<u32>, report: &mut Report) -> Result<(), CliError<Halo2>> {
+    let crs_dir = common_reference_string_dir()?;
+    let common_reference_string = match k {
+        Some(k) => read_cached_common_reference_string_for_degree(k)?,
+        None => match cached_common_reference_string_degrees().last() {
+            Some(&k) => read_cached_common_reference_string(k)?,
+            None => None,
+        },
+    }
+    .ok_or(FilesystemError::MissingCommonReferenceString(crs_dir))?;
+
+    let written_path = write_to_file(&common_reference_string, &path)?;
+    report.path_written(&path);
+    report.field("size", common_reference_string.len());
+    report.message(format!(
+        "Common reference string exported to {written_path}"
+    ));
+    Ok(())
+}
+
+fn import(
+    path: PathBuf,
+    format: SetupFormat,
+    k: Option<u32>,
+    report: &mut Report,
+) -> Result<(), CliError<Halo2>> {
+    let setup = std::fs::read(&path)
+        .map_err(|err| FilesystemError::input_read_failed("trusted setup", &path, err))?;
+
+    let common_reference_string = match format {
+        SetupFormat::Halo2 => {
+            let setup_k = common_reference_string_degree(&setup)
+                .ok_or_else(|| FilesystemError::CommonReferenceStringNotValid(path.clone()))?;
+            match k {
+                Some(k) if k != setup_k => {
+                    downsize_common_reference_string(&setup, k).ok_or_else(|| {
+                        FilesystemError::TrustedSetupNotValid(
+                            path.clone(),
+                            format!("setup only supports k = {setup_k} but k = {k} was requested"),
+                        )
+                    })?
+                }
+                _ => setup.clone(),
+            }
+        }
+        SetupFormat::Ptau => common_reference_string_from_ptau(&setup, k)
+            .map_err(|reason| FilesystemError::TrustedSetupNotValid(path.clone(), reason))?,
+    };
+
+    let k = common_reference_string_degree(&common_reference_string)
+        .ok_or(FilesystemError::CommonReferenceStringNotValid(path))?;
+
+    let replaced = write_imported_common_reference_string(&common_reference_string, &setup)?;
+
+    report.field("k", k);
+    report.field("replaced", &replaced);
+    report.path_written(&common_reference_string_location(k)?);
+    report.message(format!("Imported common reference string for k = {k}"));
+    for replaced_k in replaced {
+        report.message(format!(
+            "Removed the untrusted cached common reference string for k = {replaced_k}"
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,223 @@
+//! Running the Solidity verifier made by `eth_contract_from_vk` in an in-process EVM.
+//!
+//! Contracts are compiled with a local `solc` and deployed into an empty in-memory state, so no
+//! chain or network is involved.
+
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use acvm::{acir::native_types::WitnessMap, SmartContract};
+use clap::Args;
+use halo2_backend::Halo2;
+use revm::{
+    primitives::{CreateScheme, ExecutionResult, Output, TransactTo, TxEnv, B160},
+    InMemoryDB, EVM,
+};
+use serde::Deserialize;
+
+use super::{calldata_cmd::encode_calldata, contract::rename_contract};
+use crate::{errors::CliError, Verifier};
+
+/// Selects whether and how proofs are also verified by the Solidity verifier
+#[derive(Debug, Clone, Args)]
+pub(crate) struct EvmArgs {
+    /// Also verify the proof with the Solidity verifier contract, deployed into a local EVM
+    #[clap(long)]
+    pub(crate) evm: bool,
+
+    /// The Solidity compiler used to compile the verifier contract for `--evm`
+    #[clap(long, default_value = "solc")]
+    pub(crate) solc: PathBuf,
+}
+
+/// The largest deployed bytecode allowed on Ethereum mainnet by EIP-170.
+pub(crate) const EIP_170_CODE_SIZE_LIMIT: usize = 24_576;
+
+/// A contract deployed into a [`LocalEvm`].
+pub(crate) struct Deployment {
+    pub(crate) address: B160,
+    /// The size of the deployed bytecode in bytes.
+    pub(crate) code_size: usize,
+    pub(crate) gas_used: u64,
+}
+
+/// The outcome of calling a verifier contract.
+pub(crate) struct EvmVerification {
+    /// Whether the verifier accepted the proof.
+    pub(crate) valid: bool,
+    /// The gas used by the call, excluding deployment.
+    pub(crate) gas_used: u64,
+}
+
+/// The name the Solidity verifier is compiled under by [`verify_in_evm`].
+const VERIFIER_CONTRACT_NAME: &str = "Verifier";
+
+/// Verifies `proof` for `public_inputs` with the Solidity verifier of `verifier`.
+pub(crate) fn verify_in_evm(
+    solc: &Path,
+    verifier: &Verifier,
+    proof: &[u8],
+    public_inputs: &WitnessMap,
+) -> Result<EvmVerification, CliError<Halo2>> {
+    let contract = Halo2
+        .eth_contract_from_vk(
+            verifier.common_reference_string(),
+            verifier.verification_key(),
+        )
+        .map_err(CliError::SmartContractError)?;
+    let contract = rename_contract(&contract, VERIFIER_CONTRACT_NAME);
+    let bytecode = compile_solidity(solc, &contract, VERIFIER_CONTRACT_NAME)?;
+
+    let mut evm = LocalEvm::new();
+    let deployment = evm.deploy(bytecode)?;
+    evm.call(deployment.address, encode_calldata(proof, public_inputs))
+}
+
+/// Compiles the Solidity `source` with `solc`, returning the creation bytecode of the contract
+/// named `contract_name`.
+pub(crate) fn compile_solidity(
+    solc: &Path,
+    source: &str,
+    contract_name: &str,
+) -> Result<Vec<u8>, CliError<Halo2>> {
+    let solc_failed = |reason: String| CliError::SolcFailed(solc.to_path_buf(), reason);
+
+    let mut child = Command::new(solc)
+        .args(["--combined-json", "bin", "--optimize", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| solc_failed(err.to_string()))?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(source.as_bytes())
+        .map_err(|err| solc_failed(err.to_string()))?;
+    let output = child
+        .wait_with_output()
+        .map_err(|err| solc_failed(err.to_string()))?;
+
+    if !output.status.success() {
+        return Err(solc_failed(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ));
+    }
+
+    // Contracts are keyed by their source and name, e.g. `<stdin>:Verifier`.
+    let combined: CombinedJson = serde_json::from_slice(&output.stdout)
+        .map_err(|err| solc_failed(format!("invalid output: {err}")))?;
+    let bytecode = combined
+        .contracts
+        .iter()
+        .find(|(key, _)| key.rsplit(':').next() == Some(contract_name))
+        .map(|(_, contract)| contract.bin.as_str())
+        .filter(|bytecode| !bytecode.is_empty())
+        .ok_or_else(|| solc_failed(format!("no bytecode was output for {contract_name}")))?;
+    hex::decode(bytecode).map_err(|err| solc_failed(format!("invalid bytecode: {err}")))
+}
+
+/// The output of `solc --combined-json bin`.
+#[derive(Deserialize)]
+struct CombinedJson {
+    contracts: HashMap<String, CombinedJsonContract>,
+}
+
+#[derive(Deserialize)]
+struct CombinedJsonContract {
+    /// The hex encoded creation bytecode, empty for interfaces and abstract contracts.
+    bin: String,
+}
+
+/// An EVM with an empty in-memory state.
+///
+/// EIP-170 is not enforced, since halo2-KZG verifiers usually exceed 24 KB, so that they can still
+/// be deployed and measured.
+pub(crate) struct LocalEvm {
+    evm: EVM<InMemoryDB>,
+}
+
+impl LocalEvm {
+    pub(crate) fn new() -> Self {
+        let mut evm = EVM::new();
+        evm.database(InMemoryDB::default());
+        evm.env.cfg.limit_contract_code_size = Some(usize::MAX);
+        Self { evm }
+    }
+
+    /// Deploys the contract with creation bytecode `bytecode`.
+    pub(crate) fn deploy(&mut self, bytecode: Vec<u8>) -> Result<Deployment, CliError<Halo2>> {
+        match self.transact(TransactTo::Create(CreateScheme::Create), bytecode)? {
+            ExecutionResult::Success {
+                output: Output::Create(code, Some(address)),
+                gas_used,
+                ..
+            } => Ok(Deployment {
+                address,
+                code_size: code.len(),
+                gas_used,
+            }),
+            result => Err(CliError::EvmFailed(format!(
+                "deploying the verifier {}",
+                describe_failure(&result)
+            ))),
+        }
+    }
+
+    /// Calls the verifier at `address` with `calldata`.
+    ///
+    /// The verifier reverts on invalid proofs. A verifier returning a word accepts the proof only
+    /// if the word is non-zero.
+    pub(crate) fn call(
+        &mut self,
+        address: B160,
+        calldata: Vec<u8>,
+    ) -> Result<EvmVerification, CliError<Halo2>> {
+        let verification = match self.transact(TransactTo::Call(address), calldata)? {
+            ExecutionResult::Success {
+                gas_used, output, ..
+            } => EvmVerification {
+                valid: output.data().is_empty() || output.data().iter().any(|&byte| byte != 0),
+                gas_used,
+            },
+            ExecutionResult::Revert { gas_used, .. } | ExecutionResult::Halt { gas_used, .. } => {
+                EvmVerification {
+                    valid: false,
+                    gas_used,
+                }
+            }
+        };
+        Ok(verification)
+    }
+
+    fn transact(
+        &mut self,
+        transact_to: TransactTo,
+        data: Vec<u8>,
+    ) -> Result<ExecutionResult, CliError<Halo2>> {
+        self.evm.env.tx = TxEnv {
+            gas_limit: u64::MAX,
+            transact_to,
+            data: data.into(),
+            ..Default::default()
+        };
+        self.evm
+            .transact_commit()
+            .map_err(|err| CliError::EvmFailed(format!("{err:?}")))
+    }
+}
+
+fn describe_failure(result: &ExecutionResult) -> String {
+    match result {
+        ExecutionResult::Success { .. } => "created no contract".to_owned(),
+        ExecutionResult::Revert { output, .. } => {
+            format!("reverted with 0x{}", hex::encode(output))
+        }
+        ExecutionResult::Halt { reason, .. } => format!("halted with {reason:?}"),
+    }
+}
@@ -1,37 +1,151 @@
 use acvm::{
-    acir::{circuit::Circuit, native_types::WitnessMap},
+    acir::{
+        brillig_vm::{ForeignCallResult, Opcode as BrilligOpcode, RegisterValueOrArray, Value},
+        circuit::{opcodes::OracleData, Circuit, Opcode},
+        native_types::WitnessMap,
+    },
     pwg::{block::Blocks, solve, PartialWitnessGeneratorStatus},
+    FieldElement,
 };
 use halo2_backend::Halo2;
 use nargo::NargoError;
 use noirc_abi::{Abi, InputMap};
 
-use crate::errors::CliError;
+use crate::errors::{CliError, ExecutionError};
+
+use super::oracle::OracleResolver;
 
 pub(crate) fn execute_program(
     circuit: Circuit,
     abi: &Abi,
     inputs_map: &InputMap,
+    oracle_resolver: &mut dyn OracleResolver,
 ) -> Result<WitnessMap, CliError<Halo2>> {
     let initial_witness = abi.encode(inputs_map, None)?;
 
-    let solved_witness = execute_circuit(circuit, initial_witness)?;
+    let solved_witness = execute_circuit(circuit, initial_witness, oracle_resolver)?;
 
     Ok(solved_witness)
 }
 
+/// Solves the witness of `circuit`, asking `oracle_resolver` for the result of any oracle calls.
 pub fn execute_circuit(
     circuit: Circuit,
     mut initial_witness: WitnessMap,
-) -> Result<WitnessMap, NargoError> {
+    oracle_resolver: &mut dyn OracleResolver,
+) -> Result<WitnessMap, ExecutionError> {
     let mut blocks = Blocks::default();
-    let solver_status = solve(&Halo2, &mut initial_witness, &mut blocks, circuit.opcodes)?;
-    if matches!(
-        solver_status,
-        PartialWitnessGeneratorStatus::RequiresOracleData { .. }
-    ) {
-        todo!("Add oracle support to nargo execute")
+    let mut opcodes = circuit.opcodes;
+
+    loop {
+        let solver_status =
+            solve(&Halo2, &mut initial_witness, &mut blocks, opcodes).map_err(NargoError::from)?;
+
+        let PartialWitnessGeneratorStatus::RequiresOracleData {
+            required_oracle_data,
+            unsolved_opcodes,
+            unresolved_brillig_calls,
+        } = solver_status
+        else {
+            return Ok(initial_witness);
+        };
+
+        // Resolved calls must be solved before the opcodes which depend on their results.
+        let mut next_opcodes = Vec::with_capacity(
+            required_oracle_data.len() + unresolved_brillig_calls.len() + unsolved_opcodes.len(),
+        );
+        for oracle_data in required_oracle_data {
+            next_opcodes.push(Opcode::Oracle(resolve_oracle(
+                oracle_data,
+                oracle_resolver,
+            )?));
+        }
+        for unresolved_brillig_call in unresolved_brillig_calls {
+            let mut brillig = unresolved_brillig_call.brillig;
+            let foreign_call = unresolved_brillig_call.foreign_call_wait_info;
+
+            let inputs: Vec<_> = foreign_call.inputs.iter().map(Value::to_field).collect();
+            let outputs = call_oracle(&foreign_call.function, &inputs, oracle_resolver)?;
+            check_foreign_call_outputs(&brillig.bytecode, &foreign_call.function, outputs.len())?;
+
+            brillig.foreign_call_results.push(ForeignCallResult {
+                values: outputs.into_iter().map(Value::from).collect(),
+            });
+            next_opcodes.push(Opcode::Brillig(brillig));
+        }
+        next_opcodes.extend(unsolved_opcodes);
+
+        opcodes = next_opcodes;
+    }
+}
+
+fn resolve_oracle(
+    mut oracle_data: OracleData,
+    oracle_resolver: &mut dyn OracleResolver,
+) -> Result<OracleData, ExecutionError> {
+    let output_values = call_oracle(
+        &oracle_data.name,
+        &oracle_data.input_values,
+        oracle_resolver,
+    )?;
+
+    if output_values.len() != oracle_data.outputs.len() {
+        return Err(ExecutionError::OracleOutputMismatch {
+            name: oracle_data.name,
+            expected: oracle_data.outputs.len(),
+            actual: output_values.len(),
+        });
     }
+    oracle_data.output_values = output_values;
+
+    Ok(oracle_data)
+}
+
+/// Checks that `actual` values were returned for the Brillig foreign call `name`, as the Brillig
+/// VM panics if the count differs from the one its bytecode expects.
+///
+/// The VM does not tell which of its foreign calls is waiting, so the count is accepted if any
+/// foreign call to `name` in `bytecode` expects it.
+fn check_foreign_call_outputs(
+    bytecode: &[BrilligOpcode],
+    name: &str,
+    actual: usize,
+) -> Result<(), ExecutionError> {
+    let expected: Vec<usize> = bytecode
+        .iter()
+        .filter_map(|opcode| match opcode {
+            BrilligOpcode::ForeignCall {
+                function,
+                destination,
+                ..
+            } if function == name => Some(match destination {
+                RegisterValueOrArray::RegisterIndex(_) => 1,
+                RegisterValueOrArray::HeapArray(_, size) => *size,
+            }),
+            _ => None,
+        })
+        .collect();
 
-    Ok(initial_witness)
+    match expected.first() {
+        Some(&expected_len) if !expected.contains(&actual) => {
+            Err(ExecutionError::OracleOutputMismatch {
+                name: name.to_owned(),
+                expected: expected_len,
+                actual,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+fn call_oracle(
+    name: &str,
+    inputs: &[FieldElement],
+    oracle_resolver: &mut dyn OracleResolver,
+) -> Result<Vec<FieldElement>, ExecutionError> {
+    match oracle_resolver.resolve(name, inputs) {
+        Some(Ok(outputs)) => Ok(outputs),
+        Some(Err(reason)) => Err(ExecutionError::OracleFailed(name.to_owned(), reason)),
+        None => Err(ExecutionError::UnresolvedOracle(name.to_owned())),
+    }
 }
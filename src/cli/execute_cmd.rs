@@ -0,0 +1,107 @@
+use std::path::Path;
+
+use acvm::acir::native_types::WitnessMap;
+use clap::Args;
+use halo2_backend::Halo2;
+use nargo::artifacts::program::PreprocessedProgram;
+use noirc_abi::{
+    input_parser::{Format, InputValue},
+    InputMap, MAIN_RETURN_NAME,
+};
+
+use crate::{
+    constants::TARGET_DIR,
+    errors::{CliError, FilesystemError},
+    utils::{read_program_from_file, save_witness_to_dir},
+    Halo2Config,
+};
+
+use super::{
+    execute::execute_program,
+    inputs::ProverInputArgs,
+    oracle::{OracleArgs, OracleResolver},
+    report::Report,
+};
+
+/// Executes a circuit to calculate its return value
+#[derive(Debug, Clone, Args)]
+pub(crate) struct ExecuteCommand {
+    /// The name of the circuit build files (ACIR, proving and verification keys)
+    circuit_name: String,
+
+    /// Write the execution witness to `target/<witness_name>.tr`
+    #[clap(long, short)]
+    witness_name: Option<String>,
+
+    #[clap(flatten)]
+    inputs: ProverInputArgs,
+
+    #[clap(flatten)]
+    oracle: OracleArgs,
+}
+
+pub(crate) fn run(
+    args: ExecuteCommand,
+    config: Halo2Config,
+    report: &mut Report,
+) -> Result<(), CliError<Halo2>> {
+    let target_dir = config.program_dir.join(TARGET_DIR);
+    let circuit_build_path = target_dir.join(&args.circuit_name);
+
+    let mut oracle_resolver = args.oracle.resolvers();
+
+    let (return_value, solved_witness) = execute_with_path(
+        &config.program_dir,
+        &circuit_build_path,
+        &args.inputs,
+        &mut oracle_resolver,
+    )?;
+
+    report.message("Circuit witness successfully solved");
+    if let Some(return_value) = return_value {
+        if report.is_json() {
+            report.field("return_value", return_value_to_json(return_value)?);
+        } else {
+            report.message(format!("Circuit output: {return_value:?}"));
+        }
+    }
+    if let Some(witness_name) = args.witness_name {
+        let witness_path = save_witness_to_dir(solved_witness, &witness_name, target_dir)?;
+
+        report.path_written(&witness_path);
+        report.message(format!("Witness saved to {}", witness_path.display()));
+    }
+    Ok(())
+}
+
+fn execute_with_path<P: AsRef<Path>>(
+    program_dir: P,
+    circuit_build_path: P,
+    inputs: &ProverInputArgs,
+    oracle_resolver: &mut dyn OracleResolver,
+) -> Result<(Option<InputValue>, WitnessMap), CliError<Halo2>> {
+    let PreprocessedProgram { abi, bytecode, .. } = read_program_from_file(circuit_build_path)?;
+
+    // Parse the initial witness values from Prover.toml or Prover.json
+    let (inputs_map, _) = inputs.read_prover_inputs(program_dir, &abi)?;
+
+    let solved_witness = execute_program(bytecode, &abi, &inputs_map, oracle_resolver)?;
+
+    let (_, return_value) = abi.decode(&solved_witness)?;
+
+    Ok((return_value, solved_witness))
+}
+
+/// Converts `return_value` to JSON the same way it would be written to a `Prover.json` file.
+fn return_value_to_json(return_value: InputValue) -> Result<serde_json::Value, CliError<Halo2>> {
+    let return_map: InputMap = [(MAIN_RETURN_NAME.to_owned(), return_value)].into();
+    let serialized = Format::Json
+        .serialize(&return_map)
+        .map_err(FilesystemError::from)?;
+
+    let mut values: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(&serialized).expect("infallible: parsing JSON we just serialized");
+    Ok(values
+        .remove(MAIN_RETURN_NAME)
+        .unwrap_or(serde_json::Value::Null))
+}
@@ -0,0 +1,45 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use halo2_backend::Halo2;
+use nargo::artifacts::program::PreprocessedProgram;
+
+use super::{fs::verification_key::VerificationKeyFile, report::Report};
+use crate::constants::{TARGET_DIR, VERIFICATION_KEY_EXT};
+use crate::errors::CliError;
+use crate::utils::read_program_from_file;
+use crate::Halo2Config;
+
+/// Writes the verification key of a circuit to a standalone file, for use with `verify --vk`
+#[derive(Debug, Clone, Args)]
+pub(crate) struct ExportVkCommand {
+    /// The name of the circuit build files (ACIR, proving and verification keys)
+    circuit_name: String,
+
+    /// The file to write the verification key to, defaults to `target/<circuit_name>.vk`
+    #[clap(long, short)]
+    output: Option<PathBuf>,
+}
+
+pub(crate) fn run(
+    args: ExportVkCommand,
+    config: Halo2Config,
+    report: &mut Report,
+) -> Result<(), CliError<Halo2>> {
+    let circuit_build_path = config.program_dir.join(TARGET_DIR).join(&args.circuit_name);
+    let PreprocessedProgram {
+        bytecode,
+        verification_key,
+        ..
+    } = read_program_from_file(&circuit_build_path)?;
+
+    let vk_path = args
+        .output
+        .unwrap_or_else(|| circuit_build_path.with_extension(VERIFICATION_KEY_EXT));
+
+    let vk_path = VerificationKeyFile::new(&bytecode, &verification_key).write(&vk_path)?;
+    report.path_written(&vk_path);
+    report.message(format!("Verification key written to {}", vk_path.display()));
+
+    Ok(())
+}
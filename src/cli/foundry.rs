@@ -0,0 +1,121 @@
+//! A Foundry project around a generated verifier, so that `forge test` runs out of the box.
+//!
+//! The project needs no dependencies: the test and deploy script call the verifier directly and
+//! reach the Foundry cheatcodes through their fixed address instead of going through forge-std.
+
+use std::path::{Path, PathBuf};
+
+use crate::{
+    errors::FilesystemError,
+    utils::{create_named_dir, write_to_file},
+};
+
+const FOUNDRY_CONFIG: &str = r#"[profile.default]
+src = "."
+test = "test"
+script = "script"
+out = "out"
+libs = ["lib"]
+# Matches `solc --optimize`, which `contract --report` measures the verifier with.
+optimizer = true
+# halo2-KZG verifiers usually exceed the EIP-170 limit of 24 KB, so lift it for the local EVM.
+code_size_limit = 4294967295
+"#;
+
+/// Writes a `foundry.toml`, a test and a deploy script for the verifier `contract_name` to
+/// `project_dir`, which holds the verifier source at `contract_file`.
+///
+/// The test checks that the verifier accepts `calldata`, as encoded by `encode_calldata` for a
+/// real proof, and rejects it once the proof is tampered with. Returns the paths written.
+pub(crate) fn scaffold_foundry_project(
+    project_dir: &Path,
+    contract_file: &str,
+    contract_name: &str,
+    calldata: &[u8],
+) -> Result<Vec<PathBuf>, FilesystemError> {
+    let test_dir = create_named_dir(&project_dir.join("test"))?;
+    let script_dir = create_named_dir(&project_dir.join("script"))?;
+
+    let files = [
+        (project_dir.join("foundry.toml"), FOUNDRY_CONFIG.to_owned()),
+        (
+            test_dir.join(format!("{contract_name}.t.sol")),
+            verifier_test(contract_file, contract_name, calldata),
+        ),
+        (
+            script_dir.join(format!("Deploy{contract_name}.s.sol")),
+            deploy_script(contract_file, contract_name),
+        ),
+    ];
+
+    let mut paths_written = Vec::with_capacity(files.len());
+    for (path, contents) in files {
+        write_to_file(contents.as_bytes(), &path)?;
+        paths_written.push(path);
+    }
+    Ok(paths_written)
+}
+
+fn verifier_test(contract_file: &str, contract_name: &str, calldata: &[u8]) -> String {
+    let calldata = hex::encode(calldata);
+    format!(
+        r#"// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.0;
+
+import "../{contract_file}";
+
+contract {contract_name}Test {{
+    // The public inputs as 32 byte big-endian words followed by the proof, made by `halo2_kzg prove`.
+    bytes constant CALLDATA = hex"{calldata}";
+
+    {contract_name} verifier;
+
+    function setUp() public {{
+        verifier = new {contract_name}();
+    }}
+
+    function testAcceptsProof() public {{
+        require(verify(CALLDATA), "the verifier rejected a valid proof");
+    }}
+
+    function testRejectsTamperedProof() public {{
+        bytes memory calldata_ = CALLDATA;
+        calldata_[calldata_.length - 1] = calldata_[calldata_.length - 1] ^ bytes1(0x01);
+        require(!verify(calldata_), "the verifier accepted a tampered proof");
+    }}
+
+    // The verifier reverts on invalid proofs, or returns a zero word if it returns anything.
+    function verify(bytes memory calldata_) internal returns (bool) {{
+        (bool success, bytes memory output) = address(verifier).call(calldata_);
+        return success && (output.length == 0 || abi.decode(output, (uint256)) != 0);
+    }}
+}}
+"#
+    )
+}
+
+fn deploy_script(contract_file: &str, contract_name: &str) -> String {
+    format!(
+        r#"// SPDX-License-Identifier: UNLICENSED
+pragma solidity ^0.8.0;
+
+import "../{contract_file}";
+
+interface Vm {{
+    function startBroadcast() external;
+    function stopBroadcast() external;
+}}
+
+// Deploys the verifier, e.g. with `forge script script/Deploy{contract_name}.s.sol --rpc-url <url> --broadcast`.
+contract Deploy{contract_name} {{
+    Vm constant vm = Vm(address(uint160(uint256(keccak256("hevm cheat code")))));
+
+    function run() external returns ({contract_name} verifier) {{
+        vm.startBroadcast();
+        verifier = new {contract_name}();
+        vm.stopBroadcast();
+    }}
+}}
+"#
+    )
+}
@@ -1,32 +1,342 @@
-use std::{env, path::PathBuf};
+use std::{
+    env,
+    fs::{File, OpenOptions},
+    path::PathBuf,
+};
 
-use acvm::{acir::circuit::Circuit, CommonReferenceString};
+use acvm::{acir::circuit::Circuit, CommonReferenceString, ProofSystemCompiler};
+use fs2::FileExt;
 use halo2_backend::Halo2;
+use halo2_proofs::{
+    halo2curves::bn256::Bn256,
+    poly::{
+        commitment::{Params, ParamsProver},
+        kzg::commitment::ParamsKZG,
+    },
+};
+use serde::{Deserialize, Serialize};
 use tokio::runtime::Builder;
 
-use crate::utils::{create_named_dir, write_to_file};
+use crate::{
+    errors::{CliError, FilesystemError},
+    utils::{create_named_dir, sha256_hex, write_to_file_atomically},
+};
 
 const BACKEND_IDENTIFIER: &str = "halo2-kzg";
-const TRANSCRIPT_NAME: &str = "common-reference-string.bin";
+const TRANSCRIPT_PREFIX: &str = "k-";
+const TRANSCRIPT_EXT: &str = "bin";
+const PROVENANCE_EXT: &str = "meta.json";
+const LOCK_NAME: &str = ".lock";
 
-fn common_reference_string_location() -> PathBuf {
+/// Size in bytes of a raw encoded point in G1 and in G2.
+const G1_BYTES: usize = 64;
+const G2_BYTES: usize = 128;
+
+/// Where the cached common reference string came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CommonReferenceStringSource {
+    /// Sampled on this machine, so the toxic waste may be known to whoever generated it
+    Generated,
+    /// Imported from the transcript of a trusted setup ceremony
+    Imported,
+}
+
+/// Provenance of the cached common reference string, stored alongside it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CommonReferenceStringProvenance {
+    pub(crate) source: CommonReferenceStringSource,
+    /// Hash of the file the common reference string was imported from
+    pub(crate) source_hash: Option<String>,
+    /// Degree of the common reference string
+    pub(crate) k: u32,
+    /// Hash of the cached common reference string
+    pub(crate) hash: String,
+}
+
+impl CommonReferenceStringProvenance {
+    /// Returns whether this provenance record belongs to `common_reference_string`.
+    pub(crate) fn describes(&self, common_reference_string: &[u8]) -> bool {
+        self.hash == sha256_hex(common_reference_string)
+    }
+}
+
+/// Returns the directory holding one common reference string per degree `k`.
+pub(crate) fn common_reference_string_dir() -> Result<PathBuf, FilesystemError> {
     let cache_dir = match env::var("NARGO_BACKEND_CACHE_DIR") {
         Ok(cache_dir) => PathBuf::from(cache_dir),
-        Err(_) => dirs::home_dir().unwrap().join(".nargo").join("backends"),
+        Err(_) => dirs::home_dir()
+            .ok_or(FilesystemError::MissingHomeDirectory)?
+            .join(".nargo")
+            .join("backends"),
     };
-    cache_dir.join(BACKEND_IDENTIFIER).join(TRANSCRIPT_NAME)
+    Ok(cache_dir.join(BACKEND_IDENTIFIER))
+}
+
+pub(crate) fn common_reference_string_location(k: u32) -> Result<PathBuf, FilesystemError> {
+    Ok(common_reference_string_dir()?
+        .join(format!("{TRANSCRIPT_PREFIX}{k}"))
+        .with_extension(TRANSCRIPT_EXT))
+}
+
+fn provenance_location(k: u32) -> Result<PathBuf, FilesystemError> {
+    Ok(common_reference_string_dir()?
+        .join(format!("{TRANSCRIPT_PREFIX}{k}"))
+        .with_extension(PROVENANCE_EXT))
+}
+
+/// Returns the degrees of all cached common reference strings in ascending order.
+pub(crate) fn cached_common_reference_string_degrees() -> Vec<u32> {
+    let Some(entries) = common_reference_string_dir()
+        .ok()
+        .and_then(|crs_dir| std::fs::read_dir(crs_dir).ok())
+    else {
+        return Vec::new();
+    };
+
+    let mut degrees: Vec<u32> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name();
+            file_name
+                .to_str()?
+                .strip_prefix(TRANSCRIPT_PREFIX)?
+                .strip_suffix(&format!(".{TRANSCRIPT_EXT}"))?
+                .parse()
+                .ok()
+        })
+        .collect();
+    degrees.sort_unstable();
+
+    degrees
+}
+
+/// Locks the cache directory, shared for reading or exclusive for writing.
+///
+/// The lock is released when the returned file is dropped.
+fn lock_cache(exclusive: bool) -> Result<File, FilesystemError> {
+    let crs_dir = create_named_dir(&common_reference_string_dir()?)?;
+
+    let lock_path = crs_dir.join(LOCK_NAME);
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(|err| FilesystemError::write_failed(&lock_path, err))?;
+
+    let locked = if exclusive {
+        lock_file.lock_exclusive()
+    } else {
+        lock_file.lock_shared()
+    };
+    locked.map_err(|err| FilesystemError::read_failed(&lock_path, err))?;
+
+    Ok(lock_file)
+}
+
+/// Reads the cached common reference string of exactly degree `k`.
+///
+/// Returns `None` if there is none or it fails its integrity check.
+pub(crate) fn read_cached_common_reference_string(
+    k: u32,
+) -> Result<Option<Vec<u8>>, FilesystemError> {
+    let _lock = lock_cache(false)?;
+
+    Ok(read_verified_common_reference_string(k, false))
+}
+
+/// Reads the cached common reference string of degree `k` and checks it against its provenance
+/// record, so that a truncated or otherwise corrupted file is never used. Without a provenance
+/// record, its header and length are checked against `k` instead.
+///
+/// Corrupted files are deleted if `discard_corrupted` is set, which requires an exclusive lock.
+fn read_verified_common_reference_string(k: u32, discard_corrupted: bool) -> Option<Vec<u8>> {
+    let crs_path = common_reference_string_location(k).ok()?;
+    let common_reference_string = std::fs::read(&crs_path).ok()?;
+
+    let is_intact = match read_common_reference_string_provenance(k) {
+        Some(provenance) => provenance.describes(&common_reference_string),
+        None => {
+            header_degree(&common_reference_string) == Some(k)
+                && Some(common_reference_string.len()) == serialized_len(k)
+        }
+    };
+    if is_intact {
+        return Some(common_reference_string);
+    }
+
+    eprintln!(
+        "Warning: the cached common reference string at {} is corrupted and will be ignored",
+        crs_path.display()
+    );
+    if discard_corrupted {
+        // Best effort, the file is ignored either way and overwritten when regenerated.
+        let _ = remove_cached_common_reference_string(k);
+    }
+
+    None
 }
 
-pub(crate) fn read_cached_common_reference_string() -> Vec<u8> {
-    let crs_path = common_reference_string_location();
+/// Returns a common reference string of degree `k`, downsizing the smallest larger cached one
+/// if there is none of exactly that degree. Derived parameters are cached with the provenance
+/// of the parameters they were derived from.
+pub(crate) fn read_cached_common_reference_string_for_degree(
+    k: u32,
+) -> Result<Option<Vec<u8>>, FilesystemError> {
+    let _lock = lock_cache(true)?;
 
-    match std::fs::read(crs_path) {
-        Ok(common_reference_string) => common_reference_string,
-        Err(_) => vec![],
+    Ok(read_or_derive_common_reference_string(k).map(CachedCommonReferenceString::into_cached))
+}
+
+/// A common reference string read from the cache, or derived from a larger cached one
+enum CachedCommonReferenceString {
+    Cached(Vec<u8>),
+    /// Not cached yet, along with its provenance if that of the parameters it was derived from
+    /// is known
+    Derived(Vec<u8>, Option<CommonReferenceStringProvenance>),
+}
+
+impl CachedCommonReferenceString {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            CachedCommonReferenceString::Cached(common_reference_string)
+            | CachedCommonReferenceString::Derived(common_reference_string, _) => {
+                common_reference_string
+            }
+        }
+    }
+
+    /// Caches derived parameters, which requires an exclusive lock.
+    fn into_cached(self) -> Vec<u8> {
+        match self {
+            CachedCommonReferenceString::Cached(common_reference_string) => common_reference_string,
+            CachedCommonReferenceString::Derived(common_reference_string, Some(provenance)) => {
+                cache_best_effort(write_cached_common_reference_string_with_provenance(
+                    &common_reference_string,
+                    &provenance,
+                ));
+                common_reference_string
+            }
+            // Without a provenance record we cannot vouch for the parameters, so treat them as generated.
+            CachedCommonReferenceString::Derived(common_reference_string, None) => {
+                cache_best_effort(write_generated_common_reference_string(
+                    &common_reference_string,
+                ));
+                common_reference_string
+            }
+        }
     }
 }
 
-pub(crate) fn update_common_reference_string(
+/// Reads the cached common reference string of degree `k`, or derives one from the smallest
+/// larger cached one without caching it. Requires an exclusive lock.
+fn read_or_derive_common_reference_string(k: u32) -> Option<CachedCommonReferenceString> {
+    if let Some(common_reference_string) = read_verified_common_reference_string(k, true) {
+        return Some(CachedCommonReferenceString::Cached(common_reference_string));
+    }
+
+    let (larger_k, larger) = cached_common_reference_string_degrees()
+        .into_iter()
+        .filter(|cached_k| *cached_k > k)
+        .find_map(|cached_k| {
+            Some((
+                cached_k,
+                read_verified_common_reference_string(cached_k, true)?,
+            ))
+        })?;
+    let common_reference_string = downsize_common_reference_string(&larger, k)?;
+    let provenance = read_common_reference_string_provenance(larger_k)
+        .filter(|provenance| provenance.describes(&larger))
+        .map(|provenance| CommonReferenceStringProvenance {
+            k,
+            hash: sha256_hex(&common_reference_string),
+            ..provenance
+        });
+
+    Some(CachedCommonReferenceString::Derived(
+        common_reference_string,
+        provenance,
+    ))
+}
+
+/// Returns the smallest cached common reference string which the backend accepts for `circuit`,
+/// generating and caching a new one if none of the cached ones is large enough.
+///
+/// A cached common reference string of the right degree is read under a shared lock, so that
+/// concurrent invocations do not wait on each other. Deriving or generating one takes an
+/// exclusive lock throughout, so that concurrent invocations agree on a single common reference
+/// string rather than each generating their own.
+pub(crate) fn load_common_reference_string(circuit: &Circuit) -> Result<Vec<u8>, CliError<Halo2>> {
+    {
+        let _lock = lock_cache(false)?;
+        let cached = find_cached_common_reference_string(circuit, |k| {
+            read_verified_common_reference_string(k, false).map(CachedCommonReferenceString::Cached)
+        })
+        .map_err(CliError::CommonReferenceStringError)?;
+        if let Some(cached) = cached {
+            return Ok(cached.into_cached());
+        }
+    }
+
+    // Only the parameters which are returned are cached, not every degree derived along the way.
+    let _lock = lock_cache(true)?;
+    let cached =
+        find_cached_common_reference_string(circuit, read_or_derive_common_reference_string)
+            .map_err(CliError::CommonReferenceStringError)?;
+    if let Some(cached) = cached {
+        return Ok(cached.into_cached());
+    }
+
+    let common_reference_string = update_common_reference_string(&[], circuit)
+        .map_err(CliError::CommonReferenceStringError)?;
+    cache_best_effort(write_generated_common_reference_string(
+        &common_reference_string,
+    ));
+
+    Ok(common_reference_string)
+}
+
+/// Returns the smallest common reference string returned by `read_for_degree` which the backend
+/// accepts for `circuit` as is, trying the degrees from the estimated one up to the largest cached.
+fn find_cached_common_reference_string(
+    circuit: &Circuit,
+    read_for_degree: impl Fn(u32) -> Option<CachedCommonReferenceString>,
+) -> Result<Option<CachedCommonReferenceString>, <Halo2 as CommonReferenceString>::Error> {
+    let cached_degrees = cached_common_reference_string_degrees();
+    let Some(&max_k) = cached_degrees.last() else {
+        return Ok(None);
+    };
+    let min_k = estimate_circuit_degree(circuit)
+        .unwrap_or(cached_degrees[0])
+        .min(max_k);
+
+    for k in min_k..=max_k {
+        let Some(cached) = read_for_degree(k) else {
+            continue;
+        };
+        let updated = update_common_reference_string(cached.bytes(), circuit)?;
+        if updated == cached.bytes() {
+            return Ok(Some(cached));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Estimates the degree `k` of the circuit, i.e. the smallest `k` such that it fits in `2^k` rows.
+fn estimate_circuit_degree(circuit: &Circuit) -> Option<u32> {
+    let circuit_size = Halo2.get_exact_circuit_size(circuit).ok()?;
+
+    Some(circuit_degree(circuit_size))
+}
+
+/// Returns the smallest `k` such that a circuit of `circuit_size` rows fits in `2^k` rows.
+pub(crate) fn circuit_degree(circuit_size: u32) -> u32 {
+    circuit_size.max(1).next_power_of_two().trailing_zeros()
+}
+
+fn update_common_reference_string(
     common_reference_string: &[u8],
     circuit: &Circuit,
 ) -> Result<Vec<u8>, <Halo2 as CommonReferenceString>::Error> {
@@ -41,10 +351,219 @@ pub(crate) fn update_common_reference_string(
     runtime.block_on(fut)
 }
 
-pub(crate) fn write_cached_common_reference_string(common_reference_string: &[u8]) {
-    let crs_path = common_reference_string_location();
+pub(crate) fn read_common_reference_string_provenance(
+    k: u32,
+) -> Option<CommonReferenceStringProvenance> {
+    let provenance = std::fs::read(provenance_location(k).ok()?).ok()?;
+
+    serde_json::from_slice(&provenance).ok()
+}
+
+/// Returns whether `common_reference_string` is the cached output of a trusted setup ceremony.
+pub(crate) fn is_trusted_common_reference_string(common_reference_string: &[u8]) -> bool {
+    let Some(k) = header_degree(common_reference_string) else {
+        return false;
+    };
+
+    matches!(
+        read_common_reference_string_provenance(k),
+        Some(provenance) if provenance.source == CommonReferenceStringSource::Imported
+            && provenance.describes(common_reference_string)
+    )
+}
+
+/// Caches `common_reference_string` under its degree, recording it as locally generated.
+///
+/// Nothing is written if it is already cached.
+pub(crate) fn write_cached_common_reference_string(
+    common_reference_string: &[u8],
+) -> Result<(), FilesystemError> {
+    let _lock = lock_cache(true)?;
+
+    write_generated_common_reference_string(common_reference_string)
+}
+
+/// Failing to cache a common reference string which is already in memory should not fail the
+/// command using it, so the error is only reported.
+fn cache_best_effort(result: Result<(), FilesystemError>) {
+    if let Err(err) = result {
+        eprintln!("Warning: could not cache the common reference string. {err}");
+    }
+}
+
+fn write_generated_common_reference_string(
+    common_reference_string: &[u8],
+) -> Result<(), FilesystemError> {
+    let Some(k) = header_degree(common_reference_string) else {
+        return Ok(());
+    };
+
+    if let Some(provenance) = read_common_reference_string_provenance(k) {
+        if provenance.describes(common_reference_string) {
+            return Ok(());
+        }
+    }
+
+    let provenance = CommonReferenceStringProvenance {
+        source: CommonReferenceStringSource::Generated,
+        source_hash: None,
+        k,
+        hash: sha256_hex(common_reference_string),
+    };
+
+    write_cached_common_reference_string_with_provenance(common_reference_string, &provenance)
+}
+
+/// Caches `common_reference_string` as imported from the trusted setup file `setup`.
+///
+/// Cached common reference strings of a lower degree which were not imported are removed, as the
+/// smallest one fitting a circuit would otherwise be picked over the trusted setup. Returns their
+/// degrees.
+pub(crate) fn write_imported_common_reference_string(
+    common_reference_string: &[u8],
+    setup: &[u8],
+) -> Result<Vec<u32>, FilesystemError> {
+    let Some(k) = header_degree(common_reference_string) else {
+        return Ok(Vec::new());
+    };
+
+    let provenance = CommonReferenceStringProvenance {
+        source: CommonReferenceStringSource::Imported,
+        source_hash: Some(sha256_hex(setup)),
+        k,
+        hash: sha256_hex(common_reference_string),
+    };
+
+    let _lock = lock_cache(true)?;
+    write_cached_common_reference_string_with_provenance(common_reference_string, &provenance)?;
+
+    let replaced: Vec<u32> = cached_common_reference_string_degrees()
+        .into_iter()
+        .filter(|&cached_k| {
+            cached_k < k
+                && !matches!(
+                    read_common_reference_string_provenance(cached_k),
+                    Some(provenance) if provenance.source == CommonReferenceStringSource::Imported
+                )
+        })
+        .collect();
+    for &cached_k in &replaced {
+        remove_cached_common_reference_string(cached_k)?;
+    }
+
+    Ok(replaced)
+}
+
+/// Writes the common reference string before its provenance record. Each file is replaced
+/// atomically and readers hold a shared lock, so they never observe a mismatched pair.
+fn write_cached_common_reference_string_with_provenance(
+    common_reference_string: &[u8],
+    provenance: &CommonReferenceStringProvenance,
+) -> Result<(), FilesystemError> {
+    create_named_dir(&common_reference_string_dir()?)?;
+
+    write_to_file_atomically(
+        common_reference_string,
+        &common_reference_string_location(provenance.k)?,
+    )?;
+
+    let serialized_provenance =
+        serde_json::to_vec_pretty(provenance).expect("infallible: serializing to JSON");
+    write_to_file_atomically(&serialized_provenance, &provenance_location(provenance.k)?)?;
+
+    Ok(())
+}
+
+/// Removes all cached common reference strings, returning how many were removed.
+pub(crate) fn clear_cached_common_reference_strings() -> Result<usize, FilesystemError> {
+    let _lock = lock_cache(true)?;
+
+    let degrees = cached_common_reference_string_degrees();
+
+    for &k in &degrees {
+        remove_cached_common_reference_string(k)?;
+    }
+
+    Ok(degrees.len())
+}
+
+/// Removes the cached common reference string of degree `k` and its provenance record, which
+/// requires an exclusive lock.
+fn remove_cached_common_reference_string(k: u32) -> Result<(), FilesystemError> {
+    for path in [
+        provenance_location(k)?,
+        common_reference_string_location(k)?,
+    ] {
+        match std::fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(FilesystemError::write_failed(&path, err)),
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the degree `k` from the header of serialized parameters without decoding the points.
+fn header_degree(common_reference_string: &[u8]) -> Option<u32> {
+    let header = common_reference_string.get(..4)?;
+
+    Some(u32::from_le_bytes(header.try_into().unwrap()))
+}
+
+/// Returns the length of serialized parameters of degree `k`, i.e. the header followed by `2^k`
+/// points in G1 in both monomial and Lagrange form and two points in G2.
+fn serialized_len(k: u32) -> Option<usize> {
+    let n = 1usize.checked_shl(k)?;
+
+    n.checked_mul(2 * G1_BYTES)?.checked_add(4 + 2 * G2_BYTES)
+}
+
+/// Returns the degree `k` of the KZG parameters encoded in `common_reference_string`,
+/// i.e. the parameters support circuits with up to `2^k` rows.
+///
+/// Returns `None` if the bytes are not valid serialized parameters.
+pub(crate) fn common_reference_string_degree(common_reference_string: &[u8]) -> Option<u32> {
+    if common_reference_string.is_empty() {
+        return None;
+    }
+
+    let params = ParamsKZG::<Bn256>::read(&mut &common_reference_string[..]).ok()?;
+
+    Some(params.k())
+}
+
+/// Samples fresh KZG parameters supporting circuits with up to `2^k` rows.
+///
+/// The toxic waste is sampled locally, so these parameters are only suitable for testing.
+pub(crate) fn generate_common_reference_string_for_degree(k: u32) -> Vec<u8> {
+    let params = ParamsKZG::<Bn256>::new(k);
+
+    let mut common_reference_string = Vec::new();
+    params
+        .write(&mut common_reference_string)
+        .expect("infallible: writing to a vector");
+
+    common_reference_string
+}
+
+/// Derives parameters of degree `k` from larger parameters by truncating them.
+///
+/// Returns `None` if the bytes are not valid serialized parameters or are smaller than `2^k`.
+pub(crate) fn downsize_common_reference_string(
+    common_reference_string: &[u8],
+    k: u32,
+) -> Option<Vec<u8>> {
+    let mut params = ParamsKZG::<Bn256>::read(&mut &common_reference_string[..]).ok()?;
+    if params.k() < k {
+        return None;
+    }
+    params.downsize(k);
 
-    create_named_dir(crs_path.parent().unwrap(), "crs");
+    let mut downsized = Vec::new();
+    params
+        .write(&mut downsized)
+        .expect("infallible: writing to a vector");
 
-    write_to_file(common_reference_string, &crs_path);
+    Some(downsized)
 }
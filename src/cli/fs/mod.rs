@@ -1 +1,4 @@
 pub(crate) mod common_reference_string;
+pub(crate) mod powers_of_tau;
+pub(crate) mod proof;
+pub(crate) mod verification_key;
@@ -0,0 +1,288 @@
+//! Conversion of snarkjs `.ptau` ceremony transcripts into halo2 KZG parameters.
+//!
+//! A `.ptau` file is a sequence of sections, each prefixed by its type (`u32`) and length (`u64`).
+//! We only need the header (section 1), the powers of tau in G1 (section 2) and in G2 (section 3).
+//! Points are stored as little-endian Montgomery form coordinates, which is also the raw encoding
+//! used by `halo2curves`, so they can be decoded directly.
+
+use halo2_proofs::{
+    arithmetic::g_to_lagrange,
+    halo2curves::{
+        bn256::{Fq, G1Affine, G2Affine, G1},
+        ff::PrimeField,
+        serde::SerdeObject,
+    },
+};
+
+const PTAU_MAGIC: &[u8; 4] = b"ptau";
+const HEADER_SECTION: u32 = 1;
+const TAU_G1_SECTION: u32 = 2;
+const TAU_G2_SECTION: u32 = 3;
+/// The largest power of published ceremonies, whose transcripts are already hundreds of GB.
+const MAX_POWER: u32 = 28;
+
+/// Size in bytes of a base field element.
+const FQ_BYTES: usize = 32;
+const G1_BYTES: usize = 2 * FQ_BYTES;
+const G2_BYTES: usize = 4 * FQ_BYTES;
+
+struct Section<'a> {
+    section_type: u32,
+    data: &'a [u8],
+}
+
+/// Converts a snarkjs `.ptau` transcript into serialized halo2 KZG parameters of degree `k`.
+///
+/// If `k` is `None` the full size of the transcript is used.
+pub(crate) fn common_reference_string_from_ptau(
+    ptau: &[u8],
+    k: Option<u32>,
+) -> Result<Vec<u8>, String> {
+    let sections = read_sections(ptau)?;
+    let find_section = |section_type: u32| {
+        sections
+            .iter()
+            .find(|section| section.section_type == section_type)
+            .map(|section| section.data)
+            .ok_or_else(|| format!("missing section {section_type}"))
+    };
+
+    let power = read_header(find_section(HEADER_SECTION)?)?;
+    // The power is untrusted, so bound it before computing sizes from it.
+    if power > MAX_POWER {
+        return Err(format!(
+            "transcript claims k = {power} but at most k = {MAX_POWER} is supported"
+        ));
+    }
+    let k = k.unwrap_or(power);
+    if k > power {
+        return Err(format!(
+            "transcript only supports k = {power} but k = {k} was requested"
+        ));
+    }
+    let n = 1usize << k;
+
+    let tau_g1 = find_section(TAU_G1_SECTION)?;
+    if tau_g1.len() < n * G1_BYTES {
+        return Err("powers of tau in G1 are truncated".to_owned());
+    }
+    let g = tau_g1
+        .chunks_exact(G1_BYTES)
+        .take(n)
+        .map(|bytes| G1Affine::from_raw_bytes(bytes).ok_or("invalid G1 point".to_owned()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let tau_g2 = find_section(TAU_G2_SECTION)?;
+    if tau_g2.len() < 2 * G2_BYTES {
+        return Err("powers of tau in G2 are truncated".to_owned());
+    }
+    let g2 = G2Affine::from_raw_bytes(&tau_g2[..G2_BYTES]).ok_or("invalid G2 point")?;
+    let s_g2 =
+        G2Affine::from_raw_bytes(&tau_g2[G2_BYTES..2 * G2_BYTES]).ok_or("invalid G2 point")?;
+
+    let g_lagrange: Vec<G1Affine> = g_to_lagrange(g.iter().copied().map(G1::from).collect(), k);
+
+    // Mirrors the layout of `ParamsKZG::write` so that the result can be read back by the backend.
+    let mut common_reference_string = Vec::with_capacity(4 + 2 * n * G1_BYTES + 2 * G2_BYTES);
+    common_reference_string.extend_from_slice(&k.to_le_bytes());
+    for point in g.iter().chain(g_lagrange.iter()) {
+        point
+            .write_raw(&mut common_reference_string)
+            .expect("infallible: writing to a vector");
+    }
+    for point in [g2, s_g2] {
+        point
+            .write_raw(&mut common_reference_string)
+            .expect("infallible: writing to a vector");
+    }
+
+    Ok(common_reference_string)
+}
+
+fn read_sections(ptau: &[u8]) -> Result<Vec<Section<'_>>, String> {
+    if ptau.len() < 12 || &ptau[..4] != PTAU_MAGIC {
+        return Err("not a ptau file".to_owned());
+    }
+    let num_sections = read_u32(&ptau[8..]);
+
+    let mut sections = Vec::new();
+    let mut offset = 12;
+    for _ in 0..num_sections {
+        if ptau.len() < offset + 12 {
+            return Err("unexpected end of file".to_owned());
+        }
+        let section_type = read_u32(&ptau[offset..]);
+        let section_len = u64::from_le_bytes(ptau[offset + 4..offset + 12].try_into().unwrap());
+        offset += 12;
+
+        let end = usize::try_from(section_len)
+            .ok()
+            .and_then(|len| offset.checked_add(len))
+            .filter(|end| *end <= ptau.len())
+            .ok_or("unexpected end of file")?;
+
+        sections.push(Section {
+            section_type,
+            data: &ptau[offset..end],
+        });
+        offset = end;
+    }
+
+    Ok(sections)
+}
+
+/// Checks that the transcript is over BN254 and returns its power.
+fn read_header(header: &[u8]) -> Result<u32, String> {
+    if header.len() < 4 + FQ_BYTES + 4 {
+        return Err("header section is truncated".to_owned());
+    }
+
+    let field_size = read_u32(header) as usize;
+    let modulus = &header[4..4 + FQ_BYTES];
+    // `Fq::MODULUS` is a big-endian hex string while the transcript stores the modulus little-endian.
+    let mut expected_modulus = hex::decode(Fq::MODULUS.trim_start_matches("0x")).unwrap();
+    expected_modulus.reverse();
+    if field_size != FQ_BYTES || modulus != expected_modulus.as_slice() {
+        return Err("transcript is not over the BN254 curve".to_owned());
+    }
+
+    Ok(read_u32(&header[4 + FQ_BYTES..]))
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{
+        halo2curves::{
+            bn256::{Bn256, Fq, Fr, G1Affine, G2Affine, G1, G2},
+            ff::{Field, PrimeField},
+            group::{Curve, Group},
+            serde::SerdeObject,
+        },
+        poly::{
+            commitment::{Params, ParamsProver},
+            kzg::commitment::ParamsKZG,
+        },
+    };
+
+    use super::{
+        common_reference_string_from_ptau, FQ_BYTES, HEADER_SECTION, MAX_POWER, PTAU_MAGIC,
+        TAU_G1_SECTION, TAU_G2_SECTION,
+    };
+
+    const POWER: u32 = 2;
+
+    /// The powers of a known tau in G1, along with the generator and tau in G2.
+    fn powers_of_tau(tau: Fr, n: usize) -> (Vec<G1Affine>, G2Affine, G2Affine) {
+        let g = std::iter::successors(Some(Fr::ONE), |power| Some(*power * tau))
+            .take(n)
+            .map(|power| (G1::generator() * power).to_affine())
+            .collect();
+
+        (
+            g,
+            G2::generator().to_affine(),
+            (G2::generator() * tau).to_affine(),
+        )
+    }
+
+    fn section(section_type: u32, data: &[u8]) -> Vec<u8> {
+        let mut section = section_type.to_le_bytes().to_vec();
+        section.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        section.extend_from_slice(data);
+        section
+    }
+
+    /// Builds a `.ptau` transcript of `power` holding the points `g` in G1 and `g2` in G2.
+    fn ptau(modulus: &[u8], power: u32, g: &[G1Affine], g2: &[G2Affine]) -> Vec<u8> {
+        let mut header = (FQ_BYTES as u32).to_le_bytes().to_vec();
+        header.extend_from_slice(modulus);
+        header.extend_from_slice(&power.to_le_bytes());
+        header.extend_from_slice(&power.to_le_bytes());
+
+        let mut tau_g1 = Vec::new();
+        for point in g {
+            point.write_raw(&mut tau_g1).unwrap();
+        }
+        let mut tau_g2 = Vec::new();
+        for point in g2 {
+            point.write_raw(&mut tau_g2).unwrap();
+        }
+
+        let mut ptau = PTAU_MAGIC.to_vec();
+        ptau.extend_from_slice(&1u32.to_le_bytes());
+        ptau.extend_from_slice(&3u32.to_le_bytes());
+        ptau.extend(section(HEADER_SECTION, &header));
+        ptau.extend(section(TAU_G1_SECTION, &tau_g1));
+        ptau.extend(section(TAU_G2_SECTION, &tau_g2));
+        ptau
+    }
+
+    fn bn254_modulus() -> Vec<u8> {
+        let mut modulus = hex::decode(Fq::MODULUS.trim_start_matches("0x")).unwrap();
+        modulus.reverse();
+        modulus
+    }
+
+    #[test]
+    fn converts_ptau_into_kzg_parameters() {
+        let (g, g2, s_g2) = powers_of_tau(Fr::from(5), 1 << POWER);
+        let ptau = ptau(&bn254_modulus(), POWER, &g, &[g2, s_g2]);
+
+        let common_reference_string = common_reference_string_from_ptau(&ptau, None).unwrap();
+        let params = ParamsKZG::<Bn256>::read(&mut &common_reference_string[..]).unwrap();
+
+        assert_eq!(params.k(), POWER);
+        assert_eq!(params.get_g(), g.as_slice());
+        assert_eq!(params.g2(), g2);
+        assert_eq!(params.s_g2(), s_g2);
+    }
+
+    #[test]
+    fn rejects_ptau_not_over_bn254() {
+        let (g, g2, s_g2) = powers_of_tau(Fr::from(5), 1 << POWER);
+        let mut modulus = bn254_modulus();
+        modulus[0] ^= 1;
+        let ptau = ptau(&modulus, POWER, &g, &[g2, s_g2]);
+
+        assert_eq!(
+            common_reference_string_from_ptau(&ptau, None),
+            Err("transcript is not over the BN254 curve".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_ptau() {
+        let (g, g2, s_g2) = powers_of_tau(Fr::from(5), 1 << POWER);
+
+        let missing_points = ptau(&bn254_modulus(), POWER, &g[..3], &[g2, s_g2]);
+        assert_eq!(
+            common_reference_string_from_ptau(&missing_points, None),
+            Err("powers of tau in G1 are truncated".to_owned())
+        );
+
+        let full = ptau(&bn254_modulus(), POWER, &g, &[g2, s_g2]);
+        assert_eq!(
+            common_reference_string_from_ptau(&full[..full.len() - 1], None),
+            Err("unexpected end of file".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_oversized_ptau_header() {
+        let (g, g2, s_g2) = powers_of_tau(Fr::from(5), 1 << POWER);
+
+        for power in [MAX_POWER + 1, 64, u32::MAX] {
+            let ptau = ptau(&bn254_modulus(), power, &g, &[g2, s_g2]);
+            assert_eq!(
+                common_reference_string_from_ptau(&ptau, None),
+                Err(format!(
+                    "transcript claims k = {power} but at most k = {MAX_POWER} is supported"
+                ))
+            );
+        }
+    }
+}
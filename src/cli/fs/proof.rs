@@ -0,0 +1,165 @@
+//! The proof bundle written by `prove` and read back by `verify`.
+//!
+//! Besides the proof itself, a bundle records fingerprints of everything the proof depends on,
+//! so that `verify` can tell a proof made for another build of the circuit apart from an invalid one.
+
+use std::{collections::BTreeMap, path::Path};
+
+use acvm::{
+    acir::{
+        circuit::Circuit,
+        native_types::{Witness, WitnessMap},
+    },
+    FieldElement,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::FilesystemError, utils::sha256_hex};
+
+/// The version of the proof bundle format written by this tool.
+pub(crate) const PROOF_BUNDLE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ProofBundle {
+    /// The version of the bundle format, bumped on incompatible changes.
+    pub(crate) version: u32,
+    /// The version of `halo2_kzg` which made the proof.
+    pub(crate) tool_version: String,
+    /// The hex encoded proof.
+    pub(crate) proof: String,
+    /// The hex encoded public inputs, in the order of their witnesses.
+    pub(crate) public_inputs: Vec<String>,
+    /// SHA-256 of the serialized ACIR bytecode.
+    pub(crate) circuit_hash: String,
+    /// SHA-256 of the verification key.
+    pub(crate) verification_key_hash: String,
+    /// SHA-256 of the common reference string.
+    pub(crate) common_reference_string_hash: String,
+}
+
+impl ProofBundle {
+    pub(crate) fn new(
+        proof: &[u8],
+        public_inputs: &WitnessMap,
+        circuit: &Circuit,
+        verification_key: &[u8],
+        common_reference_string: &[u8],
+    ) -> Self {
+        Self {
+            version: PROOF_BUNDLE_VERSION,
+            tool_version: env!("CARGO_PKG_VERSION").to_owned(),
+            proof: hex::encode(proof),
+            public_inputs: encode_public_inputs(public_inputs),
+            circuit_hash: circuit_hash(circuit),
+            verification_key_hash: sha256_hex(verification_key),
+            common_reference_string_hash: sha256_hex(common_reference_string),
+        }
+    }
+
+    /// Reads the proof bundle at `proof_path`.
+    ///
+    /// Proofs written before bundles were introduced are bare hex. Those are read into a bundle
+    /// without fingerprints, for which `version` is 0.
+    pub(crate) fn read(proof_path: &Path) -> Result<Self, FilesystemError> {
+        let contents = std::fs::read_to_string(proof_path)
+            .map_err(|err| FilesystemError::input_read_failed("proof", proof_path, err))?;
+
+        if !contents.trim_start().starts_with('{') {
+            let proof = contents.trim().to_owned();
+            hex::decode(&proof).map_err(FilesystemError::HexArtifactNotValid)?;
+            return Ok(Self {
+                version: 0,
+                tool_version: String::new(),
+                proof,
+                public_inputs: Vec::new(),
+                circuit_hash: String::new(),
+                verification_key_hash: String::new(),
+                common_reference_string_hash: String::new(),
+            });
+        }
+
+        serde_json::from_str(&contents).map_err(|err| {
+            FilesystemError::ProofBundleNotValid(proof_path.to_path_buf(), err.to_string())
+        })
+    }
+
+    pub(crate) fn proof(&self) -> Result<Vec<u8>, FilesystemError> {
+        hex::decode(&self.proof).map_err(FilesystemError::HexArtifactNotValid)
+    }
+
+    /// Whether the bundle carries fingerprints which can be checked.
+    pub(crate) fn has_fingerprints(&self) -> bool {
+        self.version > 0
+    }
+}
+
+/// Hex encodes the public inputs in the order of their witnesses, which is the order the verifier expects.
+pub(crate) fn encode_public_inputs(public_inputs: &WitnessMap) -> Vec<String> {
+    public_inputs
+        .clone()
+        .into_iter()
+        .map(|(_, value)| format!("0x{}", value.to_hex()))
+        .collect()
+}
+
+/// Reads the public inputs of `circuit` from the file at `path`.
+///
+/// The file holds either a JSON array of hex encoded field elements in the order of their witnesses,
+/// or a JSON object with such an array under `public_inputs`, like a proof bundle.
+pub(crate) fn read_public_inputs(
+    path: &Path,
+    circuit: &Circuit,
+) -> Result<WitnessMap, FilesystemError> {
+    let not_valid =
+        |reason: String| FilesystemError::PublicInputsNotValid(path.to_path_buf(), reason);
+
+    let contents = std::fs::read(path)
+        .map_err(|err| FilesystemError::input_read_failed("public inputs", path, err))?;
+    let values = match serde_json::from_slice(&contents) {
+        Ok(serde_json::Value::Object(mut object)) => {
+            object.remove("public_inputs").unwrap_or_default()
+        }
+        Ok(values) => values,
+        Err(err) => return Err(not_valid(err.to_string())),
+    };
+    let values: Vec<String> =
+        serde_json::from_value(values).map_err(|err| not_valid(err.to_string()))?;
+
+    decode_public_inputs(&values, circuit).map_err(not_valid)
+}
+
+/// Assigns the hex encoded `values` to the public input witnesses of `circuit`, in order.
+pub(crate) fn decode_public_inputs(
+    values: &[String],
+    circuit: &Circuit,
+) -> Result<WitnessMap, String> {
+    let witnesses = circuit.public_inputs().indices();
+    if witnesses.len() != values.len() {
+        return Err(format!(
+            "the circuit has {} public inputs but {} were given",
+            witnesses.len(),
+            values.len()
+        ));
+    }
+
+    let public_inputs = witnesses
+        .into_iter()
+        .zip(values)
+        .map(|(witness, value)| {
+            let field = FieldElement::from_hex(value)
+                .ok_or_else(|| format!("`{value}` is not a hex encoded field element"))?;
+            Ok((Witness(witness), field))
+        })
+        .collect::<Result<BTreeMap<_, _>, String>>()?;
+
+    Ok(public_inputs.into())
+}
+
+/// Returns the hex encoded SHA-256 digest of the serialized ACIR bytecode of `circuit`.
+pub(crate) fn circuit_hash(circuit: &Circuit) -> String {
+    let mut bytecode = Vec::new();
+    circuit
+        .write(&mut bytecode)
+        .expect("infallible: writing to a vector");
+    sha256_hex(&bytecode)
+}
@@ -0,0 +1,74 @@
+//! Standalone verification key files, which let proofs be verified without the Noir package.
+
+use std::path::{Path, PathBuf};
+
+use acvm::acir::circuit::Circuit;
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::FilesystemError, utils::write_to_file};
+
+/// The version of the verification key file format written by this tool.
+pub(crate) const VERIFICATION_KEY_FILE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct VerificationKeyFile {
+    /// The version of the file format, bumped on incompatible changes.
+    pub(crate) version: u32,
+    /// The version of `halo2_kzg` which exported the key.
+    pub(crate) tool_version: String,
+    /// The hex encoded ACIR bytecode. The halo2 verifier needs the circuit to rebuild its
+    /// constraint system, so the key alone is not enough.
+    pub(crate) circuit: String,
+    /// The hex encoded verification key.
+    pub(crate) verification_key: String,
+}
+
+impl VerificationKeyFile {
+    pub(crate) fn new(circuit: &Circuit, verification_key: &[u8]) -> Self {
+        let mut bytecode = Vec::new();
+        circuit
+            .write(&mut bytecode)
+            .expect("infallible: writing to a vector");
+
+        Self {
+            version: VERIFICATION_KEY_FILE_VERSION,
+            tool_version: env!("CARGO_PKG_VERSION").to_owned(),
+            circuit: hex::encode(bytecode),
+            verification_key: hex::encode(verification_key),
+        }
+    }
+
+    /// Reads the verification key file at `path`, returning the circuit and the verification key.
+    pub(crate) fn read(path: &Path) -> Result<(Circuit, Vec<u8>), FilesystemError> {
+        let not_valid =
+            |reason: String| FilesystemError::VerificationKeyNotValid(path.to_path_buf(), reason);
+
+        let contents = std::fs::read(path)
+            .map_err(|err| FilesystemError::input_read_failed("verification key", path, err))?;
+        let file: Self =
+            serde_json::from_slice(&contents).map_err(|err| not_valid(err.to_string()))?;
+
+        if file.version > VERIFICATION_KEY_FILE_VERSION {
+            return Err(not_valid(format!(
+                "format version {} is newer than this tool supports",
+                file.version
+            )));
+        }
+
+        let bytecode = hex::decode(&file.circuit).map_err(FilesystemError::HexArtifactNotValid)?;
+        let circuit =
+            Circuit::read(bytecode.as_slice()).map_err(|err| not_valid(err.to_string()))?;
+        let verification_key =
+            hex::decode(&file.verification_key).map_err(FilesystemError::HexArtifactNotValid)?;
+
+        Ok((circuit, verification_key))
+    }
+
+    /// Writes the verification key file to `path`.
+    pub(crate) fn write(&self, path: &Path) -> Result<PathBuf, FilesystemError> {
+        let contents = serde_json::to_vec_pretty(self).expect("infallible: serializing to JSON");
+        write_to_file(&contents, path)?;
+
+        Ok(path.to_path_buf())
+    }
+}
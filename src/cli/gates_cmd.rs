@@ -1,11 +1,22 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use acvm::acir::circuit::{Circuit, Opcode};
 use acvm::ProofSystemCompiler;
 use clap::Args;
 use halo2_backend::Halo2;
 use nargo::artifacts::program::PreprocessedProgram;
+use serde::Serialize;
 
 use crate::constants::TARGET_DIR;
 use crate::errors::CliError;
 
+use super::fs::common_reference_string::circuit_degree;
+use super::report::Report;
 use crate::utils::read_program_from_file;
 use crate::Halo2Config;
 
@@ -13,20 +24,334 @@ use crate::Halo2Config;
 #[derive(Debug, Clone, Args)]
 pub(crate) struct GatesCommand {
     /// The name of the circuit build files (ACIR, proving and verification keys)
-    circuit_name: String,
+    #[clap(required_unless_present = "diff")]
+    circuit_name: Option<String>,
+
+    /// Compare the size of two compiled programs, e.g. a baseline and a new build of a circuit
+    #[clap(
+        long,
+        num_args = 2,
+        value_names = ["OLD", "NEW"],
+        conflicts_with = "circuit_name"
+    )]
+    diff: Vec<PathBuf>,
+
+    /// Fail if the circuit grows by more than this many rows, or percent with a `%` suffix
+    #[clap(long, requires = "diff")]
+    threshold: Option<Threshold>,
+}
+
+impl GatesCommand {
+    /// Whether the circuit is looked up in the Noir package rather than given as files.
+    pub(crate) fn needs_package(&self) -> bool {
+        self.diff.is_empty()
+    }
+}
+
+/// The largest increase of the circuit size allowed by `gates --diff`
+#[derive(Debug, Clone, Copy)]
+enum Threshold {
+    Rows(u32),
+    Percent(f64),
+}
+
+impl Threshold {
+    fn is_exceeded(&self, old_size: u32, new_size: u32) -> bool {
+        let increase = new_size as f64 - old_size as f64;
+        match *self {
+            Threshold::Rows(rows) => increase > rows as f64,
+            Threshold::Percent(percent) => increase > old_size as f64 * percent / 100.0,
+        }
+    }
+}
+
+impl FromStr for Threshold {
+    type Err = String;
+
+    fn from_str(threshold: &str) -> Result<Self, Self::Err> {
+        match threshold.strip_suffix('%') {
+            Some(percent) => percent
+                .parse()
+                .ok()
+                .filter(|percent: &f64| percent.is_finite() && *percent >= 0.0)
+                .map(Threshold::Percent)
+                .ok_or_else(|| format!("`{threshold}` is not a valid percentage")),
+            None => threshold
+                .parse()
+                .map(Threshold::Rows)
+                .map_err(|_| format!("`{threshold}` is not a valid number of rows")),
+        }
+    }
 }
 
-pub(crate) fn run(args: GatesCommand, config: Halo2Config) -> Result<(), CliError<Halo2>> {
-    let circuit_build_path = config.program_dir.join(TARGET_DIR).join(args.circuit_name);
+impl Display for Threshold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Threshold::Rows(rows) => write!(f, "{rows} rows"),
+            Threshold::Percent(percent) => write!(f, "{percent}%"),
+        }
+    }
+}
+
+/// The size of a circuit, broken down by kind of opcode.
+#[derive(Debug, Serialize)]
+pub(crate) struct CircuitSize {
+    /// The number of rows of the halo2 circuit.
+    pub(crate) circuit_size: u32,
+    /// The smallest `k` such that the circuit fits in `2^k` rows.
+    pub(crate) k: u32,
+    pub(crate) witnesses: u32,
+    pub(crate) public_inputs: usize,
+    /// The opcodes by kind, sorted by kind.
+    pub(crate) opcodes: Vec<OpcodeCost>,
+}
+
+#[derive(Debug, Serialize)]
+struct OpcodeDelta {
+    kind: String,
+    old_rows: u32,
+    new_rows: u32,
+    delta: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct OpcodeCost {
+    pub(crate) kind: String,
+    pub(crate) count: usize,
+    /// The rows taken by the opcodes of this kind on their own. Opcodes may share rows and lookup
+    /// tables, so these do not always add up to the circuit size.
+    pub(crate) rows: u32,
+}
+
+pub(crate) fn run(
+    args: GatesCommand,
+    config: Halo2Config,
+    report: &mut Report,
+) -> Result<(), CliError<Halo2>> {
+    if let [old_path, new_path] = args.diff.as_slice() {
+        return diff(old_path, new_path, args.threshold, report);
+    }
+
+    let circuit_name = args
+        .circuit_name
+        .expect("required unless `--diff` is present");
+    let circuit_build_path = config.program_dir.join(TARGET_DIR).join(circuit_name);
     let preprocessed_program = read_program_from_file(circuit_build_path)?;
 
     let PreprocessedProgram { bytecode, .. } = preprocessed_program;
 
-    let exact_circuit_size = Halo2
-        .get_exact_circuit_size(&bytecode)
-        .map_err(CliError::ProofSystemCompilerError)?;
+    let circuit_size = measure_circuit(&bytecode)?;
 
-    println!("Backend circuit size: {exact_circuit_size}");
+    report.message(format!(
+        "Backend circuit size: {}",
+        circuit_size.circuit_size
+    ));
+    report.message(format!("Minimum k: {}", circuit_size.k));
+    report.message(format!("Witnesses: {}", circuit_size.witnesses));
+    report.message(format!("Public inputs: {}", circuit_size.public_inputs));
+    report.message("");
+    print_opcodes(&circuit_size.opcodes, report);
+
+    report.field("circuit_size", circuit_size.circuit_size);
+    report.field("k", circuit_size.k);
+    report.field("witnesses", circuit_size.witnesses);
+    report.field("public_inputs", circuit_size.public_inputs);
+    report.field("opcodes", &circuit_size.opcodes);
 
     Ok(())
 }
+
+/// Compares the size of the compiled programs at `old_path` and `new_path`, failing if it grew by
+/// more than `threshold`.
+fn diff(
+    old_path: &Path,
+    new_path: &Path,
+    threshold: Option<Threshold>,
+    report: &mut Report,
+) -> Result<(), CliError<Halo2>> {
+    let old = measure_circuit(&read_program_from_file(old_path)?.bytecode)?;
+    let new = measure_circuit(&read_program_from_file(new_path)?.bytecode)?;
+
+    let rows_by_kind = |circuit_size: &CircuitSize| -> BTreeMap<String, u32> {
+        circuit_size
+            .opcodes
+            .iter()
+            .map(|opcode| (opcode.kind.clone(), opcode.rows))
+            .collect()
+    };
+    let old_rows = rows_by_kind(&old);
+    let new_rows = rows_by_kind(&new);
+
+    let mut kinds: Vec<&String> = old_rows.keys().chain(new_rows.keys()).collect();
+    kinds.sort();
+    kinds.dedup();
+
+    let opcodes: Vec<OpcodeDelta> = kinds
+        .into_iter()
+        .map(|kind| {
+            let old_rows = old_rows.get(kind).copied().unwrap_or_default();
+            let new_rows = new_rows.get(kind).copied().unwrap_or_default();
+            OpcodeDelta {
+                kind: kind.clone(),
+                old_rows,
+                new_rows,
+                delta: new_rows as i64 - old_rows as i64,
+            }
+        })
+        .collect();
+    let total = OpcodeDelta {
+        kind: "total".to_owned(),
+        old_rows: old.circuit_size,
+        new_rows: new.circuit_size,
+        delta: new.circuit_size as i64 - old.circuit_size as i64,
+    };
+
+    let kind_width = opcodes
+        .iter()
+        .map(|opcode| opcode.kind.len())
+        .max()
+        .unwrap_or_default()
+        .max("OPCODE".len());
+    report.message(format!(
+        "{:<kind_width$}  {:>8}  {:>8}  {:>8}",
+        "OPCODE", "OLD", "NEW", "DELTA"
+    ));
+    for opcode in opcodes.iter().chain([&total]) {
+        report.message(format!(
+            "{:<kind_width$}  {:>8}  {:>8}  {:>+8}",
+            opcode.kind, opcode.old_rows, opcode.new_rows, opcode.delta
+        ));
+    }
+    report.message(format!("k: {} -> {}", old.k, new.k));
+
+    report.field("old", &old);
+    report.field("new", &new);
+    report.field("delta", total.delta);
+    report.field("opcodes", &opcodes);
+
+    match threshold {
+        Some(threshold) if threshold.is_exceeded(old.circuit_size, new.circuit_size) => Err(
+            CliError::CircuitSizeExceeded(total.delta, threshold.to_string()),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Measures the size of `circuit` along with the rows taken by each kind of opcode.
+///
+/// The rows of a kind of opcode are measured by sizing a circuit made of only those opcodes, less
+/// the rows the backend uses for an empty circuit.
+pub(crate) fn measure_circuit(circuit: &Circuit) -> Result<CircuitSize, CliError<Halo2>> {
+    let exact_circuit_size = |circuit: &Circuit| {
+        Halo2
+            .get_exact_circuit_size(circuit)
+            .map_err(CliError::ProofSystemCompilerError)
+    };
+
+    let circuit_size = exact_circuit_size(circuit)?;
+    let empty_circuit_size = exact_circuit_size(&Circuit {
+        opcodes: Vec::new(),
+        ..circuit.clone()
+    })?;
+
+    let mut opcodes_by_kind: BTreeMap<String, Vec<Opcode>> = BTreeMap::new();
+    for opcode in &circuit.opcodes {
+        opcodes_by_kind
+            .entry(opcode_kind(opcode))
+            .or_default()
+            .push(opcode.clone());
+    }
+
+    let opcodes = opcodes_by_kind
+        .into_iter()
+        .map(|(kind, opcodes)| {
+            let count = opcodes.len();
+            let size = exact_circuit_size(&Circuit {
+                opcodes,
+                ..circuit.clone()
+            })?;
+
+            Ok(OpcodeCost {
+                kind,
+                count,
+                rows: size.saturating_sub(empty_circuit_size),
+            })
+        })
+        .collect::<Result<_, CliError<Halo2>>>()?;
+
+    Ok(CircuitSize {
+        circuit_size,
+        k: circuit_degree(circuit_size),
+        witnesses: circuit.current_witness_index,
+        public_inputs: circuit.public_inputs().indices().len(),
+        opcodes,
+    })
+}
+
+/// Returns the kind of `opcode`, naming the function of black box calls.
+fn opcode_kind(opcode: &Opcode) -> String {
+    match opcode {
+        Opcode::Arithmetic(_) => "arithmetic".to_owned(),
+        Opcode::BlackBoxFuncCall(call) => {
+            format!("black_box::{}", call.get_black_box_func().name())
+        }
+        Opcode::Directive(_) => "directive".to_owned(),
+        Opcode::Block(_) => "memory_block".to_owned(),
+        Opcode::ROM(_) => "rom".to_owned(),
+        Opcode::RAM(_) => "ram".to_owned(),
+        Opcode::Oracle(_) => "oracle".to_owned(),
+        Opcode::Brillig(_) => "brillig".to_owned(),
+    }
+}
+
+fn print_opcodes(opcodes: &[OpcodeCost], report: &Report) {
+    let kind_width = opcodes
+        .iter()
+        .map(|opcode| opcode.kind.len())
+        .max()
+        .unwrap_or_default()
+        .max("OPCODE".len());
+
+    report.message(format!(
+        "{:<kind_width$}  {:>8}  {:>8}",
+        "OPCODE", "COUNT", "ROWS"
+    ));
+    for opcode in opcodes {
+        report.message(format!(
+            "{:<kind_width$}  {:>8}  {:>8}",
+            opcode.kind, opcode.count, opcode.rows
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Threshold;
+
+    #[test]
+    fn parses_thresholds_in_rows_and_percent() {
+        assert!(matches!("100".parse(), Ok(Threshold::Rows(100))));
+        assert!(matches!("2.5%".parse(), Ok(Threshold::Percent(percent)) if percent == 2.5));
+        assert!(matches!("0%".parse(), Ok(Threshold::Percent(percent)) if percent == 0.0));
+    }
+
+    #[test]
+    fn rejects_invalid_thresholds() {
+        for threshold in ["", "-1", "1.5", "rows", "-1%", "inf%", "NaN%", "%"] {
+            assert!(
+                threshold.parse::<Threshold>().is_err(),
+                "`{threshold}` should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn compares_the_increase_against_the_threshold() {
+        assert!(!Threshold::Rows(10).is_exceeded(100, 110));
+        assert!(Threshold::Rows(10).is_exceeded(100, 111));
+        assert!(!Threshold::Rows(0).is_exceeded(100, 90));
+
+        assert!(!Threshold::Percent(5.0).is_exceeded(200, 210));
+        assert!(Threshold::Percent(5.0).is_exceeded(200, 211));
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use noirc_abi::{
+    input_parser::{Format, InputValue},
+    Abi, InputMap, MAIN_RETURN_NAME,
+};
+use serde_json::Value;
+
+use crate::{
+    constants::{PROVER_INPUT_FILE, VERIFIER_INPUT_FILE},
+    errors::FilesystemError,
+    utils::{resolve_input_format, write_inputs_to_file, InputFormat},
+};
+
+/// Selects where the prover inputs are read from
+#[derive(Debug, Clone, Args)]
+pub(crate) struct ProverInputArgs {
+    /// The name of the file (TOML or JSON) which contains the inputs for the prover
+    #[clap(long, short, default_value = PROVER_INPUT_FILE)]
+    pub(crate) prover_name: String,
+
+    /// Read the inputs for the prover from this path instead, or from stdin if `-`
+    #[clap(long, conflicts_with = "prover_name")]
+    pub(crate) inputs: Option<PathBuf>,
+
+    /// Set a prover input, overriding the one read from file. Struct fields are addressed as `a.b`
+    #[clap(long = "input", value_name = "KEY=VALUE")]
+    pub(crate) input_overrides: Vec<String>,
+
+    /// The format of the input files, detected from their extension or which file exists if not given
+    #[clap(long, value_enum)]
+    pub(crate) input_format: Option<InputFormat>,
+}
+
+impl ProverInputArgs {
+    /// Reads the prover inputs, along with the format they were read in.
+    ///
+    /// Inputs are read from `--inputs` if given and from the prover input file in `program_dir`
+    /// otherwise, after which any `--input` overrides are applied.
+    pub(crate) fn read_prover_inputs<P: AsRef<Path>>(
+        &self,
+        program_dir: P,
+        abi: &Abi,
+    ) -> Result<(InputMap, InputFormat), FilesystemError> {
+        let (input_string, input_format) = self.read_input_string(program_dir)?;
+
+        if abi.is_empty() {
+            return Ok((InputMap::new(), input_format));
+        }
+
+        let mut inputs_map = if self.input_overrides.is_empty() {
+            let format: Format = input_format.into();
+            format.parse(&input_string.unwrap_or_default(), abi)?
+        } else {
+            let input_string = self.apply_overrides(input_string, input_format, abi)?;
+            Format::Json.parse(&input_string, abi)?
+        };
+        inputs_map.remove(MAIN_RETURN_NAME);
+
+        Ok((inputs_map, input_format))
+    }
+
+    /// Returns the contents of the prover inputs and their format.
+    ///
+    /// A missing prover input file is only tolerated if its values are given as overrides.
+    fn read_input_string<P: AsRef<Path>>(
+        &self,
+        program_dir: P,
+    ) -> Result<(Option<String>, InputFormat), FilesystemError> {
+        match &self.inputs {
+            Some(path) if path.as_os_str() == "-" => {
+                let mut input_string = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut input_string)
+                    .map_err(|err| FilesystemError::read_failed(Path::new("<stdin>"), err))?;
+
+                Ok((
+                    Some(input_string),
+                    self.input_format.unwrap_or(InputFormat::Toml),
+                ))
+            }
+            Some(path) => {
+                let input_string = std::fs::read_to_string(path)
+                    .map_err(|err| FilesystemError::input_read_failed("prover input", path, err))?;
+                let input_format = self.input_format.unwrap_or_else(|| {
+                    let file_name = path.file_name().and_then(|name| name.to_str());
+                    resolve_input_format(Path::new(""), file_name.unwrap_or_default(), None).1
+                });
+
+                Ok((Some(input_string), input_format))
+            }
+            None => {
+                let (prover_name, input_format) =
+                    resolve_input_format(&program_dir, &self.prover_name, self.input_format);
+                let format: Format = input_format.into();
+                let file_path = program_dir
+                    .as_ref()
+                    .join(&prover_name)
+                    .with_extension(format.ext());
+
+                match std::fs::read_to_string(&file_path) {
+                    Ok(input_string) => Ok((Some(input_string), input_format)),
+                    Err(_) if !self.input_overrides.is_empty() => Ok((None, input_format)),
+                    Err(_) => Err(FilesystemError::MissingInputFile(prover_name, file_path)),
+                }
+            }
+        }
+    }
+
+    /// Applies the `--input` overrides on top of `input_string`, returning the result as JSON
+    /// so that it can be parsed against the ABI.
+    fn apply_overrides(
+        &self,
+        input_string: Option<String>,
+        input_format: InputFormat,
+        abi: &Abi,
+    ) -> Result<String, FilesystemError> {
+        let mut inputs = match input_string {
+            None => Value::Object(Default::default()),
+            Some(input_string) => {
+                let inputs = match input_format {
+                    InputFormat::Toml => toml::from_str::<toml::Table>(&input_string)
+                        .map_err(|err| err.to_string())
+                        .and_then(|table| {
+                            serde_json::to_value(table).map_err(|err| err.to_string())
+                        }),
+                    InputFormat::Json => {
+                        serde_json::from_str::<Value>(&input_string).map_err(|err| err.to_string())
+                    }
+                };
+                inputs.map_err(|reason| {
+                    FilesystemError::InvalidInputOverride(self.input_overrides.join(" "), reason)
+                })?
+            }
+        };
+
+        for input_override in &self.input_overrides {
+            let invalid = |reason: &str| {
+                FilesystemError::InvalidInputOverride(input_override.clone(), reason.to_owned())
+            };
+
+            let (key, value) = input_override
+                .split_once('=')
+                .ok_or_else(|| invalid("expected KEY=VALUE"))?;
+            let name = key.split('.').next().unwrap_or_default();
+            if name != MAIN_RETURN_NAME && !abi.parameters.iter().any(|param| param.name == name) {
+                return Err(invalid("the program has no such parameter"));
+            }
+
+            // Values such as `[1, 2]` or `true` are taken as JSON, anything else as a string.
+            let value = serde_json::from_str(&quote_large_integers(value))
+                .unwrap_or_else(|_| Value::String(value.to_owned()));
+
+            let mut target = &mut inputs;
+            for segment in key.split('.') {
+                let Value::Object(object) = target else {
+                    return Err(invalid("the parent of this key is not a struct"));
+                };
+                target = object
+                    .entry(segment)
+                    .or_insert_with(|| Value::Object(Default::default()));
+            }
+            *target = value;
+        }
+
+        Ok(inputs.to_string())
+    }
+}
+
+/// Quotes the integers in the JSON `value` which do not fit in a `u64` or `i64`, since serde_json
+/// would parse them as `f64` and lose precision, whereas field elements are read from strings too.
+fn quote_large_integers(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len());
+    let mut chars = value.char_indices().peekable();
+    let mut in_string = false;
+
+    while let Some((start, c)) = chars.next() {
+        if in_string {
+            quoted.push(c);
+            match c {
+                '\\' => quoted.extend(chars.next().map(|(_, escaped)| escaped)),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        if c == '"' {
+            in_string = true;
+        }
+        if !(c.is_ascii_digit() || c == '-') {
+            quoted.push(c);
+            continue;
+        }
+
+        let mut end = start + c.len_utf8();
+        while let Some(&(i, next)) = chars.peek() {
+            if !(next.is_ascii_alphanumeric() || matches!(next, '.' | '+' | '-')) {
+                break;
+            }
+            end = i + next.len_utf8();
+            chars.next();
+        }
+        let token = &value[start..end];
+        let digits = token.strip_prefix('-').unwrap_or(token);
+        let is_integer = !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit());
+        if is_integer && token.parse::<u64>().is_err() && token.parse::<i64>().is_err() {
+            quoted.push('"');
+            quoted.push_str(token);
+            quoted.push('"');
+        } else {
+            quoted.push_str(token);
+        }
+    }
+
+    quoted
+}
+
+/// Selects the files holding the prover and verifier inputs
+#[derive(Debug, Clone, Args)]
+pub(crate) struct InputArgs {
+    #[clap(flatten)]
+    pub(crate) prover: ProverInputArgs,
+
+    /// The name of the file which the inputs for the verifier are written to, in the prover's input format
+    #[clap(long, short, default_value = VERIFIER_INPUT_FILE)]
+    pub(crate) verifier_name: String,
+}
+
+impl InputArgs {
+    /// Reads the prover inputs, along with the format they were read in.
+    pub(crate) fn read_prover_inputs<P: AsRef<Path>>(
+        &self,
+        program_dir: P,
+        abi: &Abi,
+    ) -> Result<(InputMap, InputFormat), FilesystemError> {
+        self.prover.read_prover_inputs(program_dir, abi)
+    }
+
+    /// Writes the verifier inputs to `program_dir` in `input_format`.
+    pub(crate) fn write_verifier_inputs<P: AsRef<Path>>(
+        &self,
+        public_inputs: &InputMap,
+        return_value: &Option<InputValue>,
+        program_dir: P,
+        input_format: InputFormat,
+    ) -> Result<(), FilesystemError> {
+        let (verifier_name, _) =
+            resolve_input_format(&program_dir, &self.verifier_name, Some(input_format));
+
+        write_inputs_to_file(
+            public_inputs,
+            return_value,
+            program_dir,
+            &verifier_name,
+            input_format.into(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use acvm::acir::native_types::Witness;
+    use noirc_abi::{Abi, AbiParameter, AbiType, AbiVisibility};
+    use serde_json::{json, Value};
+
+    use super::{InputFormat, ProverInputArgs};
+    use crate::errors::FilesystemError;
+
+    /// The BN254 scalar field modulus minus one, which does not fit in a `u64`.
+    const LARGE: &str =
+        "21888242871839275222246405745257275088548364400416034343698204186575808495616";
+
+    /// An ABI with a field parameter of each name, as overrides are only checked by name.
+    fn abi(names: &[&str]) -> Abi {
+        Abi {
+            parameters: names
+                .iter()
+                .map(|name| AbiParameter {
+                    name: name.to_string(),
+                    typ: AbiType::Field,
+                    visibility: AbiVisibility::Private,
+                })
+                .collect(),
+            param_witnesses: names
+                .iter()
+                .zip(1..)
+                .map(|(name, index)| (name.to_string(), vec![Witness(index)]))
+                .collect(),
+            return_type: None,
+            return_witnesses: Vec::new(),
+        }
+    }
+
+    fn apply_overrides(
+        input_string: Option<&str>,
+        input_overrides: &[&str],
+    ) -> Result<Value, FilesystemError> {
+        let args = ProverInputArgs {
+            prover_name: String::new(),
+            inputs: None,
+            input_overrides: input_overrides.iter().map(|s| s.to_string()).collect(),
+            input_format: None,
+        };
+        let inputs = args.apply_overrides(
+            input_string.map(str::to_owned),
+            InputFormat::Json,
+            &abi(&["x", "a"]),
+        )?;
+
+        Ok(serde_json::from_str(&inputs).unwrap())
+    }
+
+    #[test]
+    fn keeps_large_integers_as_strings() {
+        let inputs = apply_overrides(None, &[&format!("x={LARGE}")]).unwrap();
+        assert_eq!(inputs, json!({ "x": LARGE }));
+
+        let inputs = apply_overrides(None, &[&format!("x=[1, -2, {LARGE}]")]).unwrap();
+        assert_eq!(inputs, json!({ "x": [1, -2, LARGE] }));
+    }
+
+    #[test]
+    fn takes_hex_values_as_strings() {
+        let inputs = apply_overrides(None, &["x=0x1f"]).unwrap();
+        assert_eq!(inputs, json!({ "x": "0x1f" }));
+    }
+
+    #[test]
+    fn overrides_nested_struct_fields() {
+        let inputs = apply_overrides(Some(r#"{"x": "1", "a": {"c": "2"}}"#), &["a.b=3"]).unwrap();
+        assert_eq!(inputs, json!({ "x": "1", "a": { "b": 3, "c": "2" } }));
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let err = apply_overrides(None, &["y=1"]).unwrap_err();
+        assert!(matches!(
+            err,
+            FilesystemError::InvalidInputOverride(input_override, reason)
+                if input_override == "y=1" && reason == "the program has no such parameter"
+        ));
+    }
+}
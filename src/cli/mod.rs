@@ -1,7 +1,18 @@
+pub(crate) mod batch_verify;
+pub(crate) mod calldata_cmd;
 pub(crate) mod contract;
+pub(crate) mod crs_cmd;
+pub(crate) mod evm;
 pub(crate) mod execute;
+pub(crate) mod execute_cmd;
+pub(crate) mod export_vk_cmd;
+pub(crate) mod foundry;
 pub(crate) mod fs;
 pub(crate) mod gates_cmd;
+pub(crate) mod inputs;
+pub(crate) mod oracle;
+pub(crate) mod outputs;
 pub(crate) mod prove_and_verify_cmd;
 pub(crate) mod prove_cmd;
+pub(crate) mod report;
 pub(crate) mod verify_cmd;
@@ -0,0 +1,198 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use acvm::FieldElement;
+use clap::Args;
+use serde::{Deserialize, Serialize};
+
+/// Supplies the values requested by oracle calls while a circuit's witness is being solved.
+pub trait OracleResolver {
+    /// Resolves a call to the oracle `name` with the given `inputs`.
+    ///
+    /// Returns `None` if this resolver does not know the oracle, so that another one may handle it.
+    fn resolve(
+        &mut self,
+        name: &str,
+        inputs: &[FieldElement],
+    ) -> Option<Result<Vec<FieldElement>, String>>;
+}
+
+/// Selects how oracle calls are resolved while the witness is being solved
+#[derive(Debug, Clone, Args)]
+pub(crate) struct OracleArgs {
+    /// A program to resolve oracle calls, exchanging one JSON object per line over its stdin and stdout
+    #[clap(long)]
+    pub(crate) oracle_resolver: Option<String>,
+}
+
+impl OracleArgs {
+    /// Returns the built-in resolvers, followed by the `--oracle-resolver` program if given.
+    pub(crate) fn resolvers(&self) -> OracleResolvers {
+        OracleResolvers::new(self.oracle_resolver.as_deref())
+    }
+}
+
+/// Tries each resolver in turn until one of them handles the oracle call.
+pub(crate) struct OracleResolvers(Vec<Box<dyn OracleResolver>>);
+
+impl OracleResolvers {
+    /// Returns the built-in resolvers, followed by an external resolver if `command` is set.
+    pub(crate) fn new(command: Option<&str>) -> Self {
+        let mut resolvers: Vec<Box<dyn OracleResolver>> = vec![Box::new(DebugOracleResolver)];
+        if let Some(command) = command {
+            resolvers.push(Box::new(ExternalOracleResolver::new(command)));
+        }
+
+        Self(resolvers)
+    }
+}
+
+impl OracleResolver for OracleResolvers {
+    fn resolve(
+        &mut self,
+        name: &str,
+        inputs: &[FieldElement],
+    ) -> Option<Result<Vec<FieldElement>, String>> {
+        self.0
+            .iter_mut()
+            .find_map(|resolver| resolver.resolve(name, inputs))
+    }
+}
+
+/// Handles the oracles used for debugging by printing their inputs.
+pub(crate) struct DebugOracleResolver;
+
+impl OracleResolver for DebugOracleResolver {
+    fn resolve(
+        &mut self,
+        name: &str,
+        inputs: &[FieldElement],
+    ) -> Option<Result<Vec<FieldElement>, String>> {
+        if !matches!(name, "print" | "println") {
+            return None;
+        }
+
+        let values: Vec<_> = inputs
+            .iter()
+            .map(|input| format!("0x{}", input.to_hex()))
+            .collect();
+        match values.as_slice() {
+            [value] => eprintln!("{value}"),
+            values => eprintln!("[{}]", values.join(", ")),
+        }
+
+        Some(Ok(Vec::new()))
+    }
+}
+
+/// A request sent to an external oracle resolver, one JSON object per line.
+#[derive(Serialize)]
+struct OracleRequest<'a> {
+    name: &'a str,
+    inputs: Vec<String>,
+}
+
+/// The response of an external oracle resolver, one JSON object per line.
+#[derive(Deserialize)]
+struct OracleResponse {
+    #[serde(default)]
+    outputs: Vec<String>,
+    error: Option<String>,
+}
+
+/// Forwards oracle calls to an external program over its stdin and stdout.
+///
+/// Each call is written as a line `{"name": ..., "inputs": ["0x..", ...]}` and the program must
+/// answer with a line `{"outputs": ["0x..", ...]}` or `{"error": "..."}`. Field elements are hex
+/// encoded. The program is started on the first call and kept running until the solver is done.
+pub(crate) struct ExternalOracleResolver {
+    command: String,
+    process: Option<(Child, ChildStdin, BufReader<ChildStdout>)>,
+}
+
+impl ExternalOracleResolver {
+    pub(crate) fn new(command: &str) -> Self {
+        Self {
+            command: command.to_owned(),
+            process: None,
+        }
+    }
+
+    fn call(&mut self, name: &str, inputs: &[FieldElement]) -> Result<Vec<FieldElement>, String> {
+        if self.process.is_none() {
+            let mut words = self.command.split_whitespace();
+            let program = words.next().ok_or("the oracle resolver command is empty")?;
+            let mut child = Command::new(program)
+                .args(words)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .map_err(|err| format!("could not start `{}`: {err}", self.command))?;
+            let stdin = child.stdin.take().expect("stdin is piped");
+            let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+            self.process = Some((child, stdin, stdout));
+        }
+        let (child, stdin, stdout) = self.process.as_mut().expect("process was started above");
+
+        let request = OracleRequest {
+            name,
+            inputs: inputs
+                .iter()
+                .map(|input| format!("0x{}", input.to_hex()))
+                .collect(),
+        };
+        let mut request = serde_json::to_string(&request).expect("infallible: serializing to JSON");
+        request.push('\n');
+        stdin
+            .write_all(request.as_bytes())
+            .and_then(|_| stdin.flush())
+            .map_err(|err| format!("could not write to the oracle resolver: {err}"))?;
+
+        let mut response = String::new();
+        let read = stdout
+            .read_line(&mut response)
+            .map_err(|err| format!("could not read from the oracle resolver: {err}"))?;
+        if read == 0 {
+            return Err(match child.try_wait() {
+                Ok(Some(status)) => format!("the oracle resolver exited ({status})"),
+                _ => "the oracle resolver closed its stdout".to_owned(),
+            });
+        }
+        let response: OracleResponse = serde_json::from_str(&response)
+            .map_err(|err| format!("malformed oracle resolver response: {err}"))?;
+
+        if let Some(error) = response.error {
+            return Err(error);
+        }
+        response
+            .outputs
+            .iter()
+            .map(|output| {
+                FieldElement::from_hex(output)
+                    .ok_or_else(|| format!("`{output}` is not a hex encoded field element"))
+            })
+            .collect()
+    }
+}
+
+impl OracleResolver for ExternalOracleResolver {
+    fn resolve(
+        &mut self,
+        name: &str,
+        inputs: &[FieldElement],
+    ) -> Option<Result<Vec<FieldElement>, String>> {
+        Some(self.call(name, inputs))
+    }
+}
+
+impl Drop for ExternalOracleResolver {
+    fn drop(&mut self) {
+        if let Some((mut child, stdin, _)) = self.process.take() {
+            // Closing stdin signals the resolver that there are no more calls.
+            drop(stdin);
+            let _ = child.wait();
+        }
+    }
+}
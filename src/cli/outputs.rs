@@ -0,0 +1,183 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+
+use super::{fs::proof::ProofBundle, report::Report};
+use crate::{
+    errors::FilesystemError,
+    utils::{save_proof_to_dir, write_to_file},
+};
+
+/// Encodings in which a proof can be written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum ProofFormat {
+    /// The hex encoded proof
+    Hex,
+    /// The raw proof bytes
+    Bin,
+    /// A JSON object holding the hex encoded proof, its public inputs and the circuit name
+    Json,
+}
+
+/// A proof along with what is needed to verify it, as written by `--proof-format json`.
+#[derive(Serialize)]
+struct ProofJson<'a> {
+    circuit_name: &'a str,
+    proof: &'a str,
+    /// The public inputs in the order of their witnesses, i.e. the order the verifier expects.
+    public_inputs: &'a [String],
+}
+
+/// Where a proof went once written
+pub(crate) enum WrittenProof {
+    /// The proof was saved to this path.
+    Saved(PathBuf),
+    /// The encoded proof is left for the caller to print, see [`ProofOutputArgs::report_proof`].
+    Stdout(Vec<u8>),
+}
+
+/// Selects the circuit to prove, where the proof is written to and how it is encoded
+#[derive(Debug, Clone, Args)]
+pub(crate) struct ProofOutputArgs {
+    /// The name of the proof to save to `proofs/`, if any, followed by the name of the circuit
+    /// build files (ACIR, proving and verification keys)
+    #[clap(
+        value_names = ["PROOF_NAME", "CIRCUIT_NAME"],
+        num_args = 1..=2,
+        required = true
+    )]
+    names: Vec<String>,
+
+    /// The encoding of the proof when written to `--output` or stdout [default: hex]
+    #[clap(long, value_enum)]
+    proof_format: Option<ProofFormat>,
+
+    /// Write the proof to this path instead of stdout
+    #[clap(long, short)]
+    pub(crate) output: Option<PathBuf>,
+}
+
+impl ProofOutputArgs {
+    /// Saves the proof of `circuit_name` to `proofs/<proof_name>`.
+    pub(crate) fn named(proof_name: &str, circuit_name: &str) -> Self {
+        ProofOutputArgs {
+            names: vec![proof_name.to_owned(), circuit_name.to_owned()],
+            proof_format: None,
+            output: None,
+        }
+    }
+
+    /// The name of the proof to save to `proofs/`, if one was given before the circuit name.
+    pub(crate) fn proof_name(&self) -> Option<&str> {
+        match self.names.as_slice() {
+            [proof_name, _] => Some(proof_name.as_str()),
+            _ => None,
+        }
+    }
+
+    /// The name of the circuit build files, which is always the last positional argument.
+    pub(crate) fn circuit_name(&self) -> &str {
+        self.names.last().expect("clap requires at least one name")
+    }
+
+    /// Checks the arguments which clap cannot tell apart, as the proof name is an optional
+    /// positional argument ahead of the circuit name.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if self.proof_name().is_none() {
+            return Ok(());
+        }
+        if self.output.is_some() {
+            return Err("a proof name cannot be used with '--output'".to_owned());
+        }
+        if self.proof_format.is_some() {
+            // Named proofs are always saved as proof bundles, so the format would be ignored.
+            return Err("a proof name cannot be used with '--proof-format'".to_owned());
+        }
+        Ok(())
+    }
+
+    fn proof_format(&self) -> ProofFormat {
+        self.proof_format.unwrap_or(ProofFormat::Hex)
+    }
+
+    /// Writes `proof` to the destination selected on the command line.
+    ///
+    /// Named proofs are saved as a `proof_bundle` in `proof_dir` so that `verify` can find them,
+    /// otherwise the proof is encoded in `--proof-format` and written to `--output`, or returned to
+    /// be printed to stdout.
+    pub(crate) fn write_proof<P: AsRef<Path>>(
+        &self,
+        proof: &[u8],
+        proof_bundle: &ProofBundle,
+        circuit_name: &str,
+        proof_dir: P,
+    ) -> Result<WrittenProof, FilesystemError> {
+        if let Some(proof_name) = self.proof_name() {
+            return save_proof_to_dir(proof_bundle, proof_name, proof_dir).map(WrittenProof::Saved);
+        }
+
+        let encoded_proof = match self.proof_format() {
+            ProofFormat::Hex => hex::encode(proof).into_bytes(),
+            ProofFormat::Bin => proof.to_vec(),
+            ProofFormat::Json => {
+                let proof_json = ProofJson {
+                    circuit_name,
+                    proof: &proof_bundle.proof,
+                    public_inputs: &proof_bundle.public_inputs,
+                };
+                serde_json::to_vec_pretty(&proof_json).expect("infallible: serializing to JSON")
+            }
+        };
+
+        match &self.output {
+            Some(output) => {
+                write_to_file(&encoded_proof, output)?;
+                Ok(WrittenProof::Saved(output.clone()))
+            }
+            None => Ok(WrittenProof::Stdout(encoded_proof)),
+        }
+    }
+
+    /// Reports a proof written by [`Self::write_proof`], printing it to stdout if it was not saved.
+    ///
+    /// With `--json` the proof goes into the `proof` field instead, since stdout holds the result.
+    pub(crate) fn report_proof(
+        &self,
+        written_proof: WrittenProof,
+        report: &mut Report,
+    ) -> Result<(), FilesystemError> {
+        let encoded_proof = match written_proof {
+            WrittenProof::Saved(path) => {
+                report.path_written(&path);
+                return Ok(());
+            }
+            WrittenProof::Stdout(encoded_proof) => encoded_proof,
+        };
+
+        if report.is_json() {
+            match self.proof_format() {
+                ProofFormat::Hex => report.field("proof", String::from_utf8_lossy(&encoded_proof)),
+                ProofFormat::Bin => report.field("proof", hex::encode(&encoded_proof)),
+                ProofFormat::Json => report.field(
+                    "proof",
+                    serde_json::from_slice::<serde_json::Value>(&encoded_proof)
+                        .expect("infallible: parsing JSON we just serialized"),
+                ),
+            }
+            return Ok(());
+        }
+
+        let mut stdout = std::io::stdout().lock();
+        stdout
+            .write_all(&encoded_proof)
+            .and_then(|_| match self.proof_format() {
+                ProofFormat::Bin => Ok(()),
+                ProofFormat::Hex | ProofFormat::Json => writeln!(stdout),
+            })
+            .map_err(|err| FilesystemError::write_failed(Path::new("<stdout>"), err))
+    }
+}
@@ -1,139 +1,108 @@
 use std::path::{Path, PathBuf};
 
-use acvm::ProofSystemCompiler;
 use clap::Args;
 use halo2_backend::Halo2;
-use nargo::artifacts::program::PreprocessedProgram;
-use noirc_abi::input_parser::Format;
 
 use crate::{
-    constants::{PROOFS_DIR, PROVER_INPUT_FILE, TARGET_DIR, VERIFIER_INPUT_FILE},
+    constants::{PROOFS_DIR, TARGET_DIR},
     errors::CliError,
-    utils::{
-        read_inputs_from_file, read_program_from_file, save_proof_to_dir, write_inputs_to_file,
-    },
     Halo2Config,
 };
 
 use super::{
-    execute::execute_program,
-    fs::common_reference_string::{
-        read_cached_common_reference_string, update_common_reference_string,
-        write_cached_common_reference_string,
-    },
+    crs_cmd::TrustedSetupArgs,
+    inputs::InputArgs,
+    oracle::{OracleArgs, OracleResolver},
+    outputs::{ProofOutputArgs, WrittenProof},
+    prove_cmd::{bundle_proof, load_prover},
+    report::Report,
 };
 
 /// Create proof for this program. The proof is returned as a hex encoded string.
 #[derive(Debug, Clone, Args)]
 pub(crate) struct ProveAndVerifyCommand {
-    /// The name of the proof
-    proof_name: Option<String>,
+    #[clap(flatten)]
+    output: ProofOutputArgs,
 
-    /// The name of the circuit build files (ACIR, proving and verification keys)
-    circuit_name: String,
+    #[clap(flatten)]
+    inputs: InputArgs,
 
-    /// The name of the toml file which contains the inputs for the prover
-    #[clap(long, short, default_value = PROVER_INPUT_FILE)]
-    prover_name: String,
+    #[clap(flatten)]
+    oracle: OracleArgs,
+}
 
-    /// The name of the toml file which contains the inputs for the verifier
-    #[clap(long, short, default_value = VERIFIER_INPUT_FILE)]
-    verifier_name: String,
+impl ProveAndVerifyCommand {
+    /// Checks the combinations of arguments which clap cannot express.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        self.output.validate()
+    }
 }
 
-pub(crate) fn run(args: ProveAndVerifyCommand, config: Halo2Config) -> Result<(), CliError<Halo2>> {
+pub(crate) fn run(
+    args: ProveAndVerifyCommand,
+    config: Halo2Config,
+    report: &mut Report,
+) -> Result<(), CliError<Halo2>> {
     let proof_dir = config.program_dir.join(PROOFS_DIR);
 
-    let circuit_build_path = config.program_dir.join(TARGET_DIR).join(args.circuit_name);
+    let circuit_build_path = config
+        .program_dir
+        .join(TARGET_DIR)
+        .join(args.output.circuit_name());
 
-    prove_and_verify_with_path(
-        args.proof_name,
-        args.prover_name,
-        args.verifier_name,
+    let written_proof = prove_and_verify_with_path(
+        &args.output,
+        &args.inputs,
         config.program_dir,
         proof_dir,
         circuit_build_path,
+        &mut args.oracle.resolvers(),
     )?;
+    args.output.report_proof(written_proof, report)?;
 
     Ok(())
 }
 
 pub(crate) fn prove_and_verify_with_path<P: AsRef<Path>>(
-    proof_name: Option<String>,
-    prover_name: String,
-    verifier_name: String,
+    output: &ProofOutputArgs,
+    inputs: &InputArgs,
     program_dir: P,
     proof_dir: P,
     circuit_build_path: PathBuf,
-) -> Result<Option<PathBuf>, CliError<Halo2>> {
-    let common_reference_string = read_cached_common_reference_string();
-
-    let preprocessed_program = read_program_from_file(circuit_build_path)?;
-
-    let common_reference_string =
-        update_common_reference_string(&common_reference_string, &preprocessed_program.bytecode)
-            .map_err(CliError::CommonReferenceStringError)?;
-
-    write_cached_common_reference_string(&common_reference_string);
-
-    let PreprocessedProgram {
-        abi,
-        bytecode,
-        proving_key,
-        verification_key,
-        ..
-    } = preprocessed_program;
-
-    // Parse the initial witness values from Prover.toml
-    let (inputs_map, _) =
-        read_inputs_from_file(&program_dir, prover_name.as_str(), Format::Toml, &abi)?;
-
-    let solved_witness = execute_program(bytecode.clone(), &abi, &inputs_map)?;
-
-    // Write public inputs into Verifier.toml
-    let public_abi = abi.public_abi();
-    let (public_inputs, return_value) = public_abi.decode(&solved_witness)?;
-
-    write_inputs_to_file(
-        &public_inputs,
-        &return_value,
+    oracle_resolver: &mut dyn OracleResolver,
+) -> Result<WrittenProof, CliError<Halo2>> {
+    let circuit_name = circuit_build_path
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+        .to_owned();
+    let prover = load_prover(circuit_build_path, &TrustedSetupArgs::default())?;
+
+    // Parse the initial witness values from Prover.toml or Prover.json
+    let (inputs_map, input_format) =
+        inputs.read_prover_inputs(&program_dir, &prover.program().abi)?;
+
+    let proof = prover.prove(&inputs_map, oracle_resolver)?;
+
+    // Write public inputs into Verifier.toml or Verifier.json, matching the prover inputs
+    inputs.write_verifier_inputs(
+        &proof.public_inputs,
+        &proof.return_value,
         &program_dir,
-        verifier_name.as_str(),
-        Format::Toml,
+        input_format,
     )?;
 
-    let proof = Halo2
-        .prove_with_pk(
-            &common_reference_string,
-            &bytecode,
-            solved_witness,
-            &proving_key,
-            false,
-        )
-        .map_err(CliError::ProofSystemCompilerError)?;
-
-    let public_inputs = public_abi.encode(&public_inputs, return_value)?;
-    let valid_proof = Halo2
-        .verify_with_vk(
-            &common_reference_string,
-            &proof,
-            public_inputs,
-            &bytecode,
-            &verification_key,
-            false,
-        )
-        .map_err(CliError::ProofSystemCompilerError)?;
+    let valid_proof = prover
+        .verifier()
+        .verify(&proof.proof, proof.encoded_public_inputs.clone())?;
 
     if !valid_proof {
         return Err(CliError::InvalidProof("".into()));
     }
 
-    let proof_path = if let Some(proof_name) = proof_name {
-        Some(save_proof_to_dir(&proof, &proof_name, proof_dir)?)
-    } else {
-        println!("{}", hex::encode(&proof));
-        None
-    };
+    let proof_bundle = bundle_proof(&prover, &proof);
+    let written_proof =
+        output.write_proof(&proof.proof, &proof_bundle, &circuit_name, proof_dir)?;
 
-    Ok(proof_path)
+    Ok(written_proof)
 }
@@ -1,122 +1,308 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
-use acvm::ProofSystemCompiler;
 use clap::Args;
 use halo2_backend::Halo2;
-use nargo::artifacts::program::PreprocessedProgram;
-use noirc_abi::input_parser::Format;
 
 use crate::{
-    constants::{PROOFS_DIR, PROVER_INPUT_FILE, TARGET_DIR, VERIFIER_INPUT_FILE},
-    errors::CliError,
-    utils::{
-        read_inputs_from_file, read_program_from_file, save_proof_to_dir, write_inputs_to_file,
-    },
-    Halo2Config,
+    constants::{PROOFS_DIR, TARGET_DIR},
+    errors::{CliError, FilesystemError},
+    utils::{read_program_from_file, save_proof_to_dir},
+    Halo2Config, Proof, Prover,
 };
 
 use super::{
-    execute::execute_program,
-    fs::common_reference_string::{
-        read_cached_common_reference_string, update_common_reference_string,
-        write_cached_common_reference_string,
-    },
+    crs_cmd::TrustedSetupArgs,
+    fs::proof::ProofBundle,
+    inputs::{InputArgs, ProverInputArgs},
+    oracle::{OracleArgs, OracleResolver},
+    outputs::{ProofOutputArgs, WrittenProof},
+    report::Report,
 };
 
 /// Create proof for this program. The proof is returned as a hex encoded string.
 #[derive(Debug, Clone, Args)]
 pub(crate) struct ProveCommand {
-    /// The name of the proof
-    proof_name: Option<String>,
+    #[clap(flatten)]
+    output: ProofOutputArgs,
 
-    /// The name of the circuit build files (ACIR, proving and verification keys)
-    circuit_name: String,
+    #[clap(flatten)]
+    inputs: InputArgs,
 
-    /// The name of the toml file which contains the inputs for the prover
-    #[clap(long, short, default_value = PROVER_INPUT_FILE)]
-    prover_name: String,
+    /// Prove each input file in a directory or matching a glob, saving one proof per input file
+    #[clap(
+        long,
+        value_name = "DIR_OR_GLOB",
+        conflicts_with_all = ["inputs", "output", "proof_format"]
+    )]
+    batch: Option<String>,
 
-    /// The name of the toml file which contains the inputs for the verifier
-    #[clap(long, short, default_value = VERIFIER_INPUT_FILE)]
-    verifier_name: String,
-}
+    #[clap(flatten)]
+    trusted_setup: TrustedSetupArgs,
 
-pub(crate) fn run(args: ProveCommand, config: Halo2Config) -> Result<(), CliError<Halo2>> {
-    let proof_dir = config.program_dir.join(PROOFS_DIR);
+    #[clap(flatten)]
+    oracle: OracleArgs,
+}
 
-    let circuit_build_path = config.program_dir.join(TARGET_DIR).join(args.circuit_name);
+impl ProveCommand {
+    /// Checks the combinations of arguments which clap cannot express.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        // Batch proofs are named after their input files.
+        if self.batch.is_some() && self.output.proof_name().is_some() {
+            return Err("a proof name cannot be used with '--batch'".to_owned());
+        }
+        self.output.validate()
+    }
+}
 
-    prove_with_path(
-        args.proof_name,
-        args.prover_name,
-        args.verifier_name,
+pub(crate) fn run(
+    args: ProveCommand,
+    config: Halo2Config,
+    report: &mut Report,
+) -> Result<(), CliError<Halo2>> {
+    let mut oracle_resolver = args.oracle.resolvers();
+    let context = ProveContext::new(
         config.program_dir,
-        proof_dir,
-        circuit_build_path,
-    )?;
+        args.output.circuit_name(),
+        &args.trusted_setup,
+        &mut oracle_resolver,
+    );
+
+    if let Some(batch) = args.batch {
+        return prove_batch(&batch, &args.inputs.prover, context, report);
+    }
+
+    let written_proof = prove_with_path(&args.output, &args.inputs, context)?;
+    args.output.report_proof(written_proof, report)?;
 
     Ok(())
 }
 
-pub(crate) fn prove_with_path<P: AsRef<Path>>(
-    proof_name: Option<String>,
-    prover_name: String,
-    verifier_name: String,
-    program_dir: P,
-    proof_dir: P,
+/// Where a circuit is proven from and its proofs are saved to, and what it is proven with
+pub(crate) struct ProveContext<'a> {
+    /// The package holding the prover inputs, where the verifier inputs are written to
+    program_dir: PathBuf,
+    proof_dir: PathBuf,
     circuit_build_path: PathBuf,
-) -> Result<Option<PathBuf>, CliError<Halo2>> {
-    let common_reference_string = read_cached_common_reference_string();
+    trusted_setup: &'a TrustedSetupArgs,
+    oracle_resolver: &'a mut dyn OracleResolver,
+}
 
-    let preprocessed_program = read_program_from_file(circuit_build_path)?;
+impl<'a> ProveContext<'a> {
+    /// Proves the circuit `circuit_name` of the package at `program_dir`, saving proofs to its
+    /// proofs directory.
+    pub(crate) fn new(
+        program_dir: PathBuf,
+        circuit_name: &str,
+        trusted_setup: &'a TrustedSetupArgs,
+        oracle_resolver: &'a mut dyn OracleResolver,
+    ) -> Self {
+        ProveContext {
+            proof_dir: program_dir.join(PROOFS_DIR),
+            circuit_build_path: program_dir.join(TARGET_DIR).join(circuit_name),
+            program_dir,
+            trusted_setup,
+            oracle_resolver,
+        }
+    }
+}
 
-    let common_reference_string =
-        update_common_reference_string(&common_reference_string, &preprocessed_program.bytecode)
-            .map_err(CliError::CommonReferenceStringError)?;
+pub(crate) fn prove_with_path(
+    output: &ProofOutputArgs,
+    inputs: &InputArgs,
+    context: ProveContext,
+) -> Result<WrittenProof, CliError<Halo2>> {
+    let circuit_name = context
+        .circuit_build_path
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+        .to_owned();
+    let prover = load_prover(context.circuit_build_path, context.trusted_setup)?;
 
-    write_cached_common_reference_string(&common_reference_string);
+    // Parse the initial witness values from Prover.toml or Prover.json
+    let (inputs_map, input_format) =
+        inputs.read_prover_inputs(&context.program_dir, &prover.program().abi)?;
 
-    let PreprocessedProgram {
-        abi,
-        bytecode,
-        proving_key,
-        ..
-    } = preprocessed_program;
+    let proof = prover.prove(&inputs_map, context.oracle_resolver)?;
 
-    // Parse the initial witness values from Prover.toml
-    let (inputs_map, _) =
-        read_inputs_from_file(&program_dir, prover_name.as_str(), Format::Toml, &abi)?;
+    // Write public inputs into Verifier.toml or Verifier.json, matching the prover inputs
+    inputs.write_verifier_inputs(
+        &proof.public_inputs,
+        &proof.return_value,
+        &context.program_dir,
+        input_format,
+    )?;
 
-    let solved_witness = execute_program(bytecode.clone(), &abi, &inputs_map)?;
+    let proof_bundle = bundle_proof(&prover, &proof);
+    let written_proof = output.write_proof(
+        &proof.proof,
+        &proof_bundle,
+        &circuit_name,
+        context.proof_dir,
+    )?;
 
-    // Write public inputs into Verifier.toml
-    let public_abi = abi.public_abi();
-    let (public_inputs, return_value) = public_abi.decode(&solved_witness)?;
+    Ok(written_proof)
+}
 
-    write_inputs_to_file(
-        &public_inputs,
-        &return_value,
-        &program_dir,
-        verifier_name.as_str(),
-        Format::Toml,
-    )?;
+/// Proves each input file selected by `batch`, loading the program and common reference string once.
+///
+/// Each proof is saved in the proofs directory under the name of its input file. Failing inputs
+/// are reported and skipped, after which an error is returned if any of them failed.
+fn prove_batch(
+    batch: &str,
+    inputs: &ProverInputArgs,
+    context: ProveContext,
+    report: &mut Report,
+) -> Result<(), CliError<Halo2>> {
+    let input_paths = batch_input_paths(batch)?;
+    let prover = load_prover(context.circuit_build_path, context.trusted_setup)?;
+
+    let mut failed = 0;
+    let mut results = Vec::new();
+    for input_path in &input_paths {
+        let proof_name = input_path
+            .file_stem()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+
+        // Overrides given with `--input` apply to every input file.
+        let inputs = ProverInputArgs {
+            inputs: Some(input_path.clone()),
+            ..inputs.clone()
+        };
+        let result = inputs
+            .read_prover_inputs(&context.program_dir, &prover.program().abi)
+            .map_err(CliError::from)
+            .and_then(|(inputs_map, _)| {
+                prover
+                    .prove(&inputs_map, context.oracle_resolver)
+                    .map_err(CliError::from)
+            })
+            .and_then(|proof| {
+                save_proof_to_dir(
+                    &bundle_proof(&prover, &proof),
+                    proof_name,
+                    &context.proof_dir,
+                )
+                .map_err(CliError::from)
+            });
 
-    let proof = Halo2
-        .prove_with_pk(
-            &common_reference_string,
-            &bytecode,
-            solved_witness,
-            &proving_key,
-            false,
-        )
-        .map_err(CliError::ProofSystemCompilerError)?;
-
-    let proof_path = if let Some(proof_name) = proof_name {
-        Some(save_proof_to_dir(&proof, &proof_name, proof_dir)?)
+        match result {
+            Ok(proof_path) => {
+                report.message(format!(
+                    "ok     {}: proof saved to {}",
+                    input_path.display(),
+                    proof_path.display()
+                ));
+                report.path_written(&proof_path);
+                results.push(serde_json::json!({
+                    "input": input_path,
+                    "status": "ok",
+                    "proof": proof_path,
+                }));
+            }
+            Err(err) => {
+                failed += 1;
+                report.message(format!("failed {}: {err}", input_path.display()));
+                results.push(serde_json::json!({
+                    "input": input_path,
+                    "status": "error",
+                    "error": { "kind": err.kind(), "message": err.to_string() },
+                }));
+            }
+        }
+    }
+
+    let total = input_paths.len();
+    report.message(format!(
+        "Proved {} of {total} inputs, {failed} failed",
+        total - failed
+    ));
+    report.field("proved", total - failed);
+    report.field("failed", failed);
+    report.field("inputs", results);
+
+    if failed > 0 {
+        return Err(CliError::BatchFailed(failed, total));
+    }
+    Ok(())
+}
+
+/// Returns the TOML and JSON files in the directory `batch`, or the files matching the glob `batch`.
+///
+/// Fails if two of them would be saved as the same proof.
+fn batch_input_paths(batch: &str) -> Result<Vec<PathBuf>, FilesystemError> {
+    let batch_path = Path::new(batch);
+
+    let mut input_paths: Vec<PathBuf> = if batch_path.is_dir() {
+        std::fs::read_dir(batch_path)
+            .map_err(|err| FilesystemError::read_failed(batch_path, err))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("toml" | "json")
+                )
+            })
+            .collect()
     } else {
-        println!("{}", hex::encode(&proof));
-        None
+        glob::glob(batch)
+            .map_err(|err| FilesystemError::InvalidBatch(batch.to_owned(), err.to_string()))?
+            .filter_map(Result::ok)
+            .filter(|path| path.is_file())
+            .collect()
     };
+    input_paths.sort();
+
+    if input_paths.is_empty() {
+        return Err(FilesystemError::InvalidBatch(
+            batch.to_owned(),
+            "no input files found".to_owned(),
+        ));
+    }
+
+    // Proofs are named after their input file, so inputs differing only in extension would
+    // overwrite each other's proof.
+    let mut proof_names = HashMap::new();
+    for input_path in &input_paths {
+        if let Some(other_path) = proof_names.insert(input_path.file_stem(), input_path) {
+            return Err(FilesystemError::InvalidBatch(
+                batch.to_owned(),
+                format!(
+                    "{} and {} would be saved as the same proof",
+                    other_path.display(),
+                    input_path.display()
+                ),
+            ));
+        }
+    }
+
+    Ok(input_paths)
+}
+
+/// Loads the program at `circuit_build_path` along with a common reference string for it.
+pub(super) fn load_prover(
+    circuit_build_path: PathBuf,
+    trusted_setup: &TrustedSetupArgs,
+) -> Result<Prover, CliError<Halo2>> {
+    let program = read_program_from_file(circuit_build_path)?;
+
+    let common_reference_string = trusted_setup.load_common_reference_string(&program.bytecode)?;
+
+    Ok(Prover::new(program, common_reference_string))
+}
 
-    Ok(proof_path)
+/// Bundles `proof` with the fingerprints of what `prover` made it with.
+pub(super) fn bundle_proof(prover: &Prover, proof: &Proof) -> ProofBundle {
+    let program = prover.program();
+    ProofBundle::new(
+        &proof.proof,
+        &proof.encoded_public_inputs,
+        &program.bytecode,
+        &program.verification_key,
+        prover.common_reference_string(),
+    )
 }
@@ -0,0 +1,90 @@
+use std::{fmt::Display, path::Path, time::Duration};
+
+use halo2_backend::Halo2;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::errors::CliError;
+
+/// Collects the result of a command, which is printed as text as it goes or, with `--json`,
+/// as a single JSON object once the command is done.
+///
+/// The JSON object always has the fields `command`, `status` (`ok` or `error`) and `duration_ms`,
+/// `paths_written` if the command wrote any files, `error` with its `kind` and `message` if it
+/// failed, and any fields specific to the command.
+pub(crate) struct Report {
+    json: bool,
+    paths_written: Vec<String>,
+    fields: Map<String, Value>,
+}
+
+impl Report {
+    pub(crate) fn new(json: bool) -> Self {
+        Self {
+            json,
+            paths_written: Vec::new(),
+            fields: Map::new(),
+        }
+    }
+
+    /// Whether the result is printed as JSON, in which case nothing else may be written to stdout.
+    pub(crate) fn is_json(&self) -> bool {
+        self.json
+    }
+
+    /// Prints a line of text, unless the result is printed as JSON.
+    pub(crate) fn message(&self, message: impl Display) {
+        if !self.json {
+            println!("{message}");
+        }
+    }
+
+    /// Sets a field of the JSON result.
+    pub(crate) fn field(&mut self, name: &str, value: impl Serialize) {
+        let value = serde_json::to_value(value).expect("infallible: serializing to JSON");
+        self.fields.insert(name.to_owned(), value);
+    }
+
+    /// Records that the command wrote the file at `path`.
+    pub(crate) fn path_written(&mut self, path: &Path) {
+        self.paths_written.push(path.display().to_string());
+    }
+
+    /// Prints the JSON result of `command`, which took `duration` and failed with `error` if any.
+    pub(crate) fn print_json(
+        self,
+        command: &str,
+        duration: Duration,
+        error: Option<&CliError<Halo2>>,
+    ) {
+        let mut result = Map::new();
+        result.insert("command".to_owned(), command.into());
+        result.insert(
+            "status".to_owned(),
+            if error.is_some() { "error" } else { "ok" }.into(),
+        );
+        result.insert(
+            "duration_ms".to_owned(),
+            (duration.as_millis() as u64).into(),
+        );
+        if !self.paths_written.is_empty() {
+            result.insert("paths_written".to_owned(), self.paths_written.into());
+        }
+        if let Some(error) = error {
+            result.insert(
+                "error".to_owned(),
+                serde_json::json!({
+                    "kind": error.kind(),
+                    "message": error.to_string(),
+                    "exit_code": error.exit_code() as i32,
+                }),
+            );
+        }
+        result.extend(self.fields);
+
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&result).expect("infallible: serializing to JSON")
+        );
+    }
+}
@@ -1,100 +1,301 @@
-use super::fs::common_reference_string::{
-    read_cached_common_reference_string, update_common_reference_string,
-    write_cached_common_reference_string,
+use super::batch_verify::{verify_batch, ReportFormat};
+use super::crs_cmd::TrustedSetupArgs;
+use super::evm::{verify_in_evm, EvmArgs};
+use super::fs::{
+    proof::{
+        circuit_hash, encode_public_inputs, read_public_inputs, ProofBundle, PROOF_BUNDLE_VERSION,
+    },
+    verification_key::VerificationKeyFile,
+};
+use super::report::Report;
+use crate::utils::{
+    read_inputs_from_file, read_program_from_file, resolve_input_format, sha256_hex, InputFormat,
 };
-use crate::utils::{load_hex_data, read_inputs_from_file, read_program_from_file};
-use crate::Halo2Config;
 use crate::{
     constants::{PROOFS_DIR, PROOF_EXT, TARGET_DIR, VERIFIER_INPUT_FILE},
     errors::CliError,
 };
+use crate::{Halo2Config, Verifier};
 
-use acvm::ProofSystemCompiler;
+use acvm::acir::{circuit::Circuit, native_types::WitnessMap};
 use clap::Args;
 use halo2_backend::Halo2;
 use nargo::artifacts::program::PreprocessedProgram;
+use noirc_abi::Abi;
 
-use noirc_abi::input_parser::Format;
 use std::path::{Path, PathBuf};
 
 /// Given a proof and a program, verify whether the proof is valid
 #[derive(Debug, Clone, Args)]
 pub(crate) struct VerifyCommand {
-    /// The proof to verify
-    proof: String,
-
-    /// The name of the circuit build files (ACIR, proving and verification keys)
-    circuit_name: String,
+    /// The proof to verify followed by the name of the circuit build files (ACIR, proving and
+    /// verification keys). Only the circuit name is given with `--batch`
+    #[clap(
+        value_names = ["PROOF", "CIRCUIT_NAME"],
+        num_args = 1..=2,
+        required_unless_present = "vk"
+    )]
+    names: Vec<String>,
 
-    /// The name of the toml file which contains the inputs for the verifier
+    /// The name of the file (TOML or JSON) which contains the inputs for the verifier
     #[clap(long, short, default_value = VERIFIER_INPUT_FILE)]
     verifier_name: String,
+
+    /// The format of the input files, detected from their extension or which file exists if not given
+    #[clap(long, value_enum)]
+    input_format: Option<InputFormat>,
+
+    /// Verify against this verification key file, as written by `export-vk`, instead of the package
+    #[clap(
+        long,
+        requires_all = ["proof_path", "public_inputs"],
+        conflicts_with = "names"
+    )]
+    vk: Option<PathBuf>,
+
+    /// The proof file to verify against `--vk`
+    #[clap(long = "proof", requires = "vk")]
+    proof_path: Option<PathBuf>,
+
+    /// A JSON file holding the public inputs to verify against `--vk`, as hex encoded field elements
+    #[clap(long, requires = "vk")]
+    public_inputs: Option<PathBuf>,
+
+    /// Verify every proof in these directories or files in parallel, against the public inputs
+    /// saved in each proof
+    #[clap(
+        long,
+        value_name = "DIR_OR_FILE",
+        num_args = 1..,
+        conflicts_with_all = ["vk", "evm"]
+    )]
+    batch: Vec<PathBuf>,
+
+    /// The format of the `--batch` report
+    #[clap(long, value_enum, default_value_t = ReportFormat::Table, requires = "batch")]
+    report: ReportFormat,
+
+    #[clap(flatten)]
+    evm: EvmArgs,
+
+    #[clap(flatten)]
+    trusted_setup: TrustedSetupArgs,
+}
+
+impl VerifyCommand {
+    /// Whether the proof is verified against the Noir package rather than standalone files.
+    pub(crate) fn needs_package(&self) -> bool {
+        self.vk.is_none()
+    }
+
+    /// Checks the number of positional arguments, which depends on `--batch`.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        match (self.names.len(), self.batch.is_empty()) {
+            (2, false) => Err("'--batch' takes the circuit name only, not a proof".to_owned()),
+            (1, true) if self.vk.is_none() => {
+                Err("the following required arguments were not provided: <CIRCUIT_NAME>".to_owned())
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
-pub(crate) fn run(args: VerifyCommand, config: Halo2Config) -> Result<(), CliError<Halo2>> {
+pub(crate) fn run(
+    args: VerifyCommand,
+    config: Halo2Config,
+    report: &mut Report,
+) -> Result<(), CliError<Halo2>> {
+    if let (Some(vk_path), Some(proof_path), Some(public_inputs_path)) =
+        (args.vk, args.proof_path, args.public_inputs)
+    {
+        let (verifier, public_inputs) =
+            load_with_vk(&vk_path, &public_inputs_path, &args.trusted_setup)?;
+        return verify_proof(proof_path, &verifier, public_inputs, &args.evm, report);
+    }
+
+    if !args.batch.is_empty() {
+        let [circuit_name] = args.names.as_slice() else {
+            unreachable!("checked by `validate`")
+        };
+        let circuit_build_path = config.program_dir.join(TARGET_DIR).join(circuit_name);
+        return verify_batch(
+            &args.batch,
+            circuit_build_path,
+            &args.trusted_setup,
+            args.report,
+            report,
+        );
+    }
+
+    let [proof, circuit_name] = args.names.as_slice() else {
+        unreachable!("checked by `validate`")
+    };
+
     let proof_path = config
         .program_dir
         .join(PROOFS_DIR)
-        .join(&args.proof)
+        .join(proof)
         .with_extension(PROOF_EXT);
 
-    let circuit_build_path = config.program_dir.join(TARGET_DIR).join(args.circuit_name);
+    let circuit_build_path = config.program_dir.join(TARGET_DIR).join(circuit_name);
 
-    verify_with_path(
+    let (verifier, public_inputs) = load_with_path(
         &config.program_dir,
-        proof_path,
         &circuit_build_path,
         args.verifier_name,
-    )
+        args.input_format,
+        &args.trusted_setup,
+    )?;
+    verify_proof(proof_path, &verifier, public_inputs, &args.evm, report)
 }
 
-fn verify_with_path<P: AsRef<Path>>(
+/// Loads the verifier of the circuit at `circuit_build_path` along with the public inputs read
+/// from `verifier_name`.
+fn load_with_path<P: AsRef<Path>>(
     program_dir: P,
-    proof_path: PathBuf,
     circuit_build_path: P,
     verifier_name: String,
-) -> Result<(), CliError<Halo2>> {
-    let common_reference_string = read_cached_common_reference_string();
-
-    let preprocessed_program = read_program_from_file(circuit_build_path)?;
-
-    let common_reference_string =
-        update_common_reference_string(&common_reference_string, &preprocessed_program.bytecode)
-            .map_err(CliError::CommonReferenceStringError)?;
-
-    write_cached_common_reference_string(&common_reference_string);
-
+    input_format: Option<InputFormat>,
+    trusted_setup: &TrustedSetupArgs,
+) -> Result<(Verifier, WitnessMap), CliError<Halo2>> {
     let PreprocessedProgram {
         abi,
         bytecode,
         verification_key,
         ..
-    } = preprocessed_program;
+    } = read_program_from_file(circuit_build_path)?;
 
-    // Load public inputs (if any) from `verifier_name`.
+    let public_inputs = read_verifier_inputs(program_dir, &verifier_name, input_format, abi)?;
+
+    let verifier = load_verifier(bytecode, verification_key, trusted_setup)?;
+
+    Ok((verifier, public_inputs))
+}
+
+/// Reads the public inputs (if any) from `verifier_name` and encodes them for the verifier.
+pub(super) fn read_verifier_inputs<P: AsRef<Path>>(
+    program_dir: P,
+    verifier_name: &str,
+    input_format: Option<InputFormat>,
+    abi: Abi,
+) -> Result<WitnessMap, CliError<Halo2>> {
     let public_abi = abi.public_abi();
+    let (verifier_name, input_format) =
+        resolve_input_format(&program_dir, verifier_name, input_format);
     let (public_inputs_map, return_value) = read_inputs_from_file(
         program_dir,
-        verifier_name.as_str(),
-        Format::Toml,
+        &verifier_name,
+        input_format.into(),
         &public_abi,
     )?;
 
-    let public_inputs = public_abi.encode(&public_inputs_map, return_value)?;
-    let proof = load_hex_data(&proof_path)?;
-
-    let valid_proof = Halo2
-        .verify_with_vk(
-            &common_reference_string,
-            &proof,
-            public_inputs,
-            &bytecode,
-            &verification_key,
-            false,
-        )
-        .map_err(CliError::ProofSystemCompilerError)?;
-
-    if valid_proof {
+    public_abi
+        .encode(&public_inputs_map, return_value)
+        .map_err(CliError::from)
+}
+
+/// Loads the verifier from a verification key file along with the public inputs from a public inputs file.
+fn load_with_vk(
+    vk_path: &Path,
+    public_inputs_path: &Path,
+    trusted_setup: &TrustedSetupArgs,
+) -> Result<(Verifier, WitnessMap), CliError<Halo2>> {
+    let (bytecode, verification_key) = VerificationKeyFile::read(vk_path)?;
+    let public_inputs = read_public_inputs(public_inputs_path, &bytecode)?;
+
+    let verifier = load_verifier(bytecode, verification_key, trusted_setup)?;
+
+    Ok((verifier, public_inputs))
+}
+
+/// Verifies the proof at `proof_path`, and with `--evm` checks that the Solidity verifier agrees.
+fn verify_proof(
+    proof_path: PathBuf,
+    verifier: &Verifier,
+    public_inputs: WitnessMap,
+    evm: &EvmArgs,
+    report: &mut Report,
+) -> Result<(), CliError<Halo2>> {
+    let proof_bundle = ProofBundle::read(&proof_path)?;
+
+    if !evm.evm {
+        return check_proof(proof_path, &proof_bundle, verifier, public_inputs);
+    }
+
+    let proof = proof_bundle.proof()?;
+    let evm_verification = verify_in_evm(&evm.solc, verifier, &proof, &public_inputs)?;
+
+    report.field("evm_valid", evm_verification.valid);
+    report.field("evm_gas_used", evm_verification.gas_used);
+    report.message(format!(
+        "Solidity verifier {} the proof using {} gas",
+        if evm_verification.valid {
+            "accepted"
+        } else {
+            "rejected"
+        },
+        evm_verification.gas_used
+    ));
+
+    let result = check_proof(proof_path.clone(), &proof_bundle, verifier, public_inputs);
+    match (result, evm_verification.valid) {
+        (Ok(()), true) => Ok(()),
+        (Ok(()), false) => Err(CliError::EvmVerifierRejected(proof_path)),
+        (Err(CliError::InvalidProof(_)), true) => Err(CliError::EvmVerifierAccepted(proof_path)),
+        (Err(err), _) => Err(err),
+    }
+}
+
+/// Creates a verifier for `bytecode` along with a common reference string for it.
+pub(super) fn load_verifier(
+    bytecode: Circuit,
+    verification_key: Vec<u8>,
+    trusted_setup: &TrustedSetupArgs,
+) -> Result<Verifier, CliError<Halo2>> {
+    let common_reference_string = trusted_setup.load_common_reference_string(&bytecode)?;
+
+    Ok(Verifier::new(
+        bytecode,
+        verification_key,
+        common_reference_string,
+    ))
+}
+
+/// Checks the fingerprints of `proof_bundle` and verifies its proof.
+pub(super) fn check_proof(
+    proof_path: PathBuf,
+    proof_bundle: &ProofBundle,
+    verifier: &Verifier,
+    public_inputs: WitnessMap,
+) -> Result<(), CliError<Halo2>> {
+    if proof_bundle.version > PROOF_BUNDLE_VERSION {
+        return Err(CliError::UnsupportedProofBundle(
+            proof_path,
+            proof_bundle.version,
+        ));
+    }
+
+    // Tell apart proofs made for another build of the circuit or setup from invalid ones.
+    if proof_bundle.has_fingerprints() {
+        if proof_bundle.circuit_hash != circuit_hash(verifier.circuit()) {
+            return Err(CliError::CircuitMismatch(proof_path));
+        }
+        if proof_bundle.verification_key_hash != sha256_hex(verifier.verification_key()) {
+            return Err(CliError::VerificationKeyMismatch(proof_path));
+        }
+        if proof_bundle.common_reference_string_hash
+            != sha256_hex(verifier.common_reference_string())
+        {
+            return Err(CliError::CommonReferenceStringMismatch(proof_path));
+        }
+        if proof_bundle.public_inputs != encode_public_inputs(&public_inputs) {
+            return Err(CliError::PublicInputsMismatch(proof_path));
+        }
+    }
+
+    let proof = proof_bundle.proof()?;
+
+    if verifier.verify(&proof, public_inputs)? {
         Ok(())
     } else {
         Err(CliError::InvalidProof(proof_path))
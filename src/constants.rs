@@ -15,3 +15,11 @@ pub(crate) const VERIFIER_INPUT_FILE: &str = "Verifier";
 // Extensions
 /// The extension for files containing circuit proofs.
 pub(crate) const PROOF_EXT: &str = "proof";
+/// The extension for standalone verification key files.
+pub(crate) const VERIFICATION_KEY_EXT: &str = "vk";
+/// The extension for files containing solved witnesses.
+pub(crate) const WITNESS_EXT: &str = "tr";
+/// The extension for Solidity verifier contracts.
+pub(crate) const CONTRACT_EXT: &str = "sol";
+/// The extension for compiled programs.
+pub(crate) const PROGRAM_EXT: &str = "json";
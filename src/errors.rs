@@ -2,12 +2,44 @@ use acvm::{
     acir::native_types::WitnessMapError, Backend, CommonReferenceString, ProofSystemCompiler,
     SmartContract,
 };
+use halo2_backend::Halo2;
 use hex::FromHexError;
 use nargo::NargoError;
 use noirc_abi::errors::{AbiError, InputParserError};
-use std::path::PathBuf;
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
 use thiserror::Error;
 
+/// The exit code of the process for each class of failure.
+///
+/// These are part of the command line interface and documented in the README, so existing values
+/// must not change. Code 2 is left to clap, which uses it for invalid command line arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExitCode {
+    /// An unexpected failure which fits none of the other classes.
+    Failure = 1,
+    /// A proof did not verify, or was made for another circuit, setup or public inputs.
+    InvalidProof = 3,
+    /// The inputs, proof or key files given to the command are missing or malformed.
+    InvalidInput = 4,
+    /// The Noir package or its build artifacts are missing or malformed.
+    MissingArtifact = 5,
+    /// The circuit could not be executed for the given inputs.
+    ExecutionFailed = 6,
+    /// The proving backend failed.
+    BackendError = 7,
+    /// The common reference string is missing, malformed or not trusted.
+    CommonReferenceStringError = 8,
+    /// A file could not be read or written.
+    IoError = 9,
+    /// The circuit grew by more than the threshold given to `gates --diff`.
+    CircuitSizeExceeded = 10,
+    /// A verifier contract exceeds the EIP-170 bytecode size limit under `contract --strict`.
+    ContractTooLarge = 11,
+}
+
 #[derive(Debug, Error)]
 pub(crate) enum FilesystemError {
     #[error("Error: {} is not a valid path\nRun either `nargo compile` to generate missing build artifacts or `nargo prove` to construct a proof", .0.display())]
@@ -15,9 +47,40 @@ pub(crate) enum FilesystemError {
     #[error("Error: could not parse hex build artifact (proof, proving and/or verification keys, ACIR checksum) ({0})")]
     HexArtifactNotValid(FromHexError),
     #[error(
-        " Error: cannot find {0}.toml file.\n Expected location: {1:?} \n Please generate this file at the expected location."
+        " Error: cannot find {0} input file.\n Expected location: {1:?} \n Please generate this file at the expected location."
     )]
-    MissingTomlFile(String, PathBuf),
+    MissingInputFile(String, PathBuf),
+    #[error("Error: cannot find the {0} file {}", .1.display())]
+    MissingFile(&'static str, PathBuf),
+    #[error("Error: invalid input `{0}`: {1}")]
+    InvalidInputOverride(String, String),
+    #[error("Error: {} is not a valid proof bundle: {1}", .0.display())]
+    ProofBundleNotValid(PathBuf, String),
+    #[error("Error: {} is not a valid verification key file: {1}", .0.display())]
+    VerificationKeyNotValid(PathBuf, String),
+    #[error("Error: {} does not contain valid public inputs: {1}", .0.display())]
+    PublicInputsNotValid(PathBuf, String),
+    #[error("Error: invalid batch `{0}`: {1}")]
+    InvalidBatch(String, String),
+    #[error("Error: no suitable common reference string is cached in {}\nRun `halo2_kzg crs ensure --k <k>` to generate one", .0.display())]
+    MissingCommonReferenceString(PathBuf),
+    #[error("Error: {} does not contain valid halo2 KZG parameters", .0.display())]
+    CommonReferenceStringNotValid(PathBuf),
+    #[error("Error: could not import the trusted setup at {}: {1}", .0.display())]
+    TrustedSetupNotValid(PathBuf, String),
+
+    #[error("Error: could not write {}: {1}", .0.display())]
+    WriteFailed(PathBuf, std::io::Error),
+    #[error("Error: could not read {}: {1}", .0.display())]
+    ReadFailed(PathBuf, std::io::Error),
+    #[error("Error: permission denied for {}: {1}", .0.display())]
+    PermissionDenied(PathBuf, std::io::Error),
+    #[error("Error: {} is not a valid compiled program: {1}\nRun `nargo compile` to rebuild it", .0.display())]
+    ProgramNotValid(PathBuf, serde_json::Error),
+    #[error("Error: cannot find a Nargo.toml in {} or any of its parent directories", .0.display())]
+    MissingManifest(PathBuf),
+    #[error("Error: cannot find the home directory to cache common reference strings in\nSet NARGO_BACKEND_CACHE_DIR to choose another directory")]
+    MissingHomeDirectory,
 
     /// Input parsing error
     #[error(transparent)]
@@ -28,11 +91,223 @@ pub(crate) enum FilesystemError {
     WitnessMapSerialization(#[from] WitnessMapError),
 }
 
+impl FilesystemError {
+    pub(crate) fn write_failed(path: &Path, err: std::io::Error) -> Self {
+        match err.kind() {
+            ErrorKind::PermissionDenied => {
+                FilesystemError::PermissionDenied(path.to_path_buf(), err)
+            }
+            _ => FilesystemError::WriteFailed(path.to_path_buf(), err),
+        }
+    }
+
+    pub(crate) fn read_failed(path: &Path, err: std::io::Error) -> Self {
+        match err.kind() {
+            ErrorKind::PermissionDenied => {
+                FilesystemError::PermissionDenied(path.to_path_buf(), err)
+            }
+            _ => FilesystemError::ReadFailed(path.to_path_buf(), err),
+        }
+    }
+
+    /// Maps a failure to read the `file_kind` file at `path` given to the command, which is an
+    /// invalid input rather than a missing build artifact if it does not exist.
+    pub(crate) fn input_read_failed(
+        file_kind: &'static str,
+        path: &Path,
+        err: std::io::Error,
+    ) -> Self {
+        match err.kind() {
+            ErrorKind::NotFound => FilesystemError::MissingFile(file_kind, path.to_path_buf()),
+            _ => FilesystemError::read_failed(path, err),
+        }
+    }
+
+    /// A stable identifier of the error, as reported by `--json`.
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            FilesystemError::PathNotValid(_) => "path_not_valid",
+            FilesystemError::HexArtifactNotValid(_) => "hex_artifact_not_valid",
+            FilesystemError::MissingInputFile(..) => "missing_input_file",
+            FilesystemError::MissingFile(..) => "missing_file",
+            FilesystemError::InvalidInputOverride(..) => "invalid_input_override",
+            FilesystemError::ProofBundleNotValid(..) => "proof_bundle_not_valid",
+            FilesystemError::VerificationKeyNotValid(..) => "verification_key_not_valid",
+            FilesystemError::PublicInputsNotValid(..) => "public_inputs_not_valid",
+            FilesystemError::InvalidBatch(..) => "invalid_batch",
+            FilesystemError::MissingCommonReferenceString(_) => "missing_common_reference_string",
+            FilesystemError::CommonReferenceStringNotValid(_) => {
+                "common_reference_string_not_valid"
+            }
+            FilesystemError::TrustedSetupNotValid(..) => "trusted_setup_not_valid",
+            FilesystemError::WriteFailed(..) => "write_failed",
+            FilesystemError::ReadFailed(..) => "read_failed",
+            FilesystemError::PermissionDenied(..) => "permission_denied",
+            FilesystemError::ProgramNotValid(..) => "program_not_valid",
+            FilesystemError::MissingManifest(_) => "missing_manifest",
+            FilesystemError::MissingHomeDirectory => "missing_home_directory",
+            FilesystemError::InputParserError(_) => "input_parser_error",
+            FilesystemError::WitnessMapSerialization(_) => "witness_map_serialization",
+        }
+    }
+
+    pub(crate) fn exit_code(&self) -> ExitCode {
+        match self {
+            FilesystemError::PathNotValid(_)
+            | FilesystemError::HexArtifactNotValid(_)
+            | FilesystemError::ProgramNotValid(..)
+            | FilesystemError::MissingManifest(_) => ExitCode::MissingArtifact,
+            FilesystemError::MissingInputFile(..)
+            | FilesystemError::MissingFile(..)
+            | FilesystemError::InvalidInputOverride(..)
+            | FilesystemError::ProofBundleNotValid(..)
+            | FilesystemError::VerificationKeyNotValid(..)
+            | FilesystemError::PublicInputsNotValid(..)
+            | FilesystemError::InvalidBatch(..)
+            | FilesystemError::InputParserError(_) => ExitCode::InvalidInput,
+            FilesystemError::MissingCommonReferenceString(_)
+            | FilesystemError::CommonReferenceStringNotValid(_)
+            | FilesystemError::TrustedSetupNotValid(..) => ExitCode::CommonReferenceStringError,
+            FilesystemError::WriteFailed(..)
+            | FilesystemError::ReadFailed(..)
+            | FilesystemError::PermissionDenied(..)
+            | FilesystemError::MissingHomeDirectory => ExitCode::IoError,
+            FilesystemError::WitnessMapSerialization(_) => ExitCode::Failure,
+        }
+    }
+}
+
+/// An error while solving the witness of a circuit
+#[derive(Debug, Error)]
+pub enum ExecutionError {
+    /// No oracle resolver handles the oracle with this name
+    #[error("No oracle resolver handles the oracle `{0}`\nPass `--oracle-resolver <command>` to resolve it with an external program")]
+    UnresolvedOracle(String),
+    /// The oracle resolver failed to resolve the oracle, for the given reason
+    #[error("Oracle `{0}` failed: {1}")]
+    OracleFailed(String, String),
+    /// The oracle resolver returned a different number of values than the call expects
+    #[error("Oracle `{name}` returned {actual} values but {expected} were expected")]
+    OracleOutputMismatch {
+        /// The name of the oracle
+        name: String,
+        /// The number of values the call expects
+        expected: usize,
+        /// The number of values the resolver returned
+        actual: usize,
+    },
+
+    /// Error from Nargo
+    #[error(transparent)]
+    NargoError(#[from] NargoError),
+}
+
+impl ExecutionError {
+    /// A stable identifier of the error, as reported by `--json`.
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            ExecutionError::UnresolvedOracle(_) => "unresolved_oracle",
+            ExecutionError::OracleFailed(..) => "oracle_failed",
+            ExecutionError::OracleOutputMismatch { .. } => "oracle_output_mismatch",
+            ExecutionError::NargoError(_) => "nargo_error",
+        }
+    }
+}
+
+/// An error while proving or verifying
+#[derive(Debug, Error)]
+pub enum ProofError {
+    /// ABI encoding/decoding error
+    #[error(transparent)]
+    AbiError(#[from] AbiError),
+
+    /// Error while solving the witness
+    #[error(transparent)]
+    ExecutionError(#[from] ExecutionError),
+
+    /// Backend error caused by a function on the ProofSystemCompiler trait
+    #[error(transparent)]
+    ProofSystemCompilerError(<Halo2 as ProofSystemCompiler>::Error),
+
+    /// Backend error caused by a function on the CommonReferenceString trait
+    #[error(transparent)]
+    CommonReferenceStringError(<Halo2 as CommonReferenceString>::Error),
+
+    /// The common reference string cache could not be read or written
+    #[error("{0}")]
+    CommonReferenceStringCacheError(String),
+}
+
+impl ProofError {
+    /// A stable identifier of the error, as reported by `--json`.
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            ProofError::AbiError(_) => "abi_error",
+            ProofError::ExecutionError(err) => err.kind(),
+            ProofError::ProofSystemCompilerError(_) => "proof_system_compiler_error",
+            ProofError::CommonReferenceStringError(_) => "common_reference_string_error",
+            ProofError::CommonReferenceStringCacheError(_) => "common_reference_string_cache_error",
+        }
+    }
+
+    pub(crate) fn exit_code(&self) -> ExitCode {
+        match self {
+            ProofError::AbiError(_) => ExitCode::InvalidInput,
+            ProofError::ExecutionError(_) => ExitCode::ExecutionFailed,
+            ProofError::ProofSystemCompilerError(_) => ExitCode::BackendError,
+            ProofError::CommonReferenceStringError(_) => ExitCode::CommonReferenceStringError,
+            ProofError::CommonReferenceStringCacheError(_) => ExitCode::IoError,
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub(crate) enum CliError<B: Backend> {
     #[error("Failed to verify proof {}", .0.display())]
     InvalidProof(PathBuf),
 
+    #[error("Proof {} uses bundle format version {1}, which is newer than this tool supports", .0.display())]
+    UnsupportedProofBundle(PathBuf, u32),
+
+    #[error("Proof {} was made for a different build of the circuit\nRecompile the circuit it was made for or prove it again", .0.display())]
+    CircuitMismatch(PathBuf),
+
+    #[error("Proof {} was made with a different verification key", .0.display())]
+    VerificationKeyMismatch(PathBuf),
+
+    #[error("Proof {} was made with a different common reference string\nImport the common reference string it was made with using `halo2_kzg crs import`", .0.display())]
+    CommonReferenceStringMismatch(PathBuf),
+
+    #[error("Proof {} was made for different public inputs than the ones given", .0.display())]
+    PublicInputsMismatch(PathBuf),
+
+    #[error("Failed to prove {0} of {1} inputs")]
+    BatchFailed(usize, usize),
+
+    #[error("{0} of {1} proofs failed to verify")]
+    BatchVerificationFailed(usize, usize),
+
+    #[error("The circuit grew by {0} rows, more than the threshold of {1}")]
+    CircuitSizeExceeded(i64, String),
+
+    #[error("Contract {0} exceeds the EIP-170 limit of {1} bytes")]
+    ContractTooLarge(String, usize),
+
+    #[error("Failed to compile the Solidity verifier with {}: {1}", .0.display())]
+    SolcFailed(PathBuf, String),
+
+    #[error("The local EVM failed: {0}")]
+    EvmFailed(String),
+
+    #[error("Proof {} is valid but the Solidity verifier rejects it", .0.display())]
+    EvmVerifierRejected(PathBuf),
+
+    #[error("Proof {} is invalid but the Solidity verifier accepts it", .0.display())]
+    EvmVerifierAccepted(PathBuf),
+
+    #[error("The cached common reference string is not the output of a trusted setup\nImport one with `halo2_kzg crs import` or drop `--require-trusted-setup`")]
+    UntrustedCommonReferenceString,
+
     /// ABI encoding/decoding error
     #[error(transparent)]
     AbiError(#[from] AbiError),
@@ -45,6 +320,14 @@ pub(crate) enum CliError<B: Backend> {
     #[error(transparent)]
     NargoError(#[from] NargoError),
 
+    /// Error while solving the witness
+    #[error(transparent)]
+    ExecutionError(#[from] ExecutionError),
+
+    /// Error while proving or verifying
+    #[error(transparent)]
+    ProofError(#[from] ProofError),
+
     /// Backend error caused by a function on the SmartContract trait
     #[error(transparent)]
     SmartContractError(<B as SmartContract>::Error), // Unfortunately, Rust won't let us `impl From` over an Associated Type on a generic
@@ -57,3 +340,64 @@ pub(crate) enum CliError<B: Backend> {
     #[error(transparent)]
     CommonReferenceStringError(<B as CommonReferenceString>::Error), // Unfortunately, Rust won't let us `impl From` over an Associated Type on a generic
 }
+
+impl<B: Backend> CliError<B> {
+    /// A stable identifier of the error, as reported by `--json`.
+    ///
+    /// Errors wrapping a filesystem, execution or proof error report the kind of the wrapped error.
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            CliError::InvalidProof(_) => "invalid_proof",
+            CliError::UnsupportedProofBundle(..) => "unsupported_proof_bundle",
+            CliError::CircuitMismatch(_) => "circuit_mismatch",
+            CliError::VerificationKeyMismatch(_) => "verification_key_mismatch",
+            CliError::CommonReferenceStringMismatch(_) => "common_reference_string_mismatch",
+            CliError::PublicInputsMismatch(_) => "public_inputs_mismatch",
+            CliError::BatchFailed(..) => "batch_failed",
+            CliError::BatchVerificationFailed(..) => "batch_verification_failed",
+            CliError::CircuitSizeExceeded(..) => "circuit_size_exceeded",
+            CliError::ContractTooLarge(..) => "contract_too_large",
+            CliError::SolcFailed(..) => "solc_failed",
+            CliError::EvmFailed(_) => "evm_failed",
+            CliError::EvmVerifierRejected(_) => "evm_verifier_rejected",
+            CliError::EvmVerifierAccepted(_) => "evm_verifier_accepted",
+            CliError::UntrustedCommonReferenceString => "untrusted_common_reference_string",
+            CliError::AbiError(_) => "abi_error",
+            CliError::FilesystemError(err) => err.kind(),
+            CliError::NargoError(_) => "nargo_error",
+            CliError::ExecutionError(err) => err.kind(),
+            CliError::ProofError(err) => err.kind(),
+            CliError::SmartContractError(_) => "smart_contract_error",
+            CliError::ProofSystemCompilerError(_) => "proof_system_compiler_error",
+            CliError::CommonReferenceStringError(_) => "common_reference_string_error",
+        }
+    }
+
+    /// The exit code of the process when the command fails with this error.
+    pub(crate) fn exit_code(&self) -> ExitCode {
+        match self {
+            CliError::InvalidProof(_)
+            | CliError::CircuitMismatch(_)
+            | CliError::VerificationKeyMismatch(_)
+            | CliError::CommonReferenceStringMismatch(_)
+            | CliError::PublicInputsMismatch(_)
+            | CliError::BatchVerificationFailed(..) => ExitCode::InvalidProof,
+            CliError::UnsupportedProofBundle(..) | CliError::AbiError(_) => ExitCode::InvalidInput,
+            CliError::BatchFailed(..) => ExitCode::Failure,
+            CliError::CircuitSizeExceeded(..) => ExitCode::CircuitSizeExceeded,
+            CliError::ContractTooLarge(..) => ExitCode::ContractTooLarge,
+            CliError::UntrustedCommonReferenceString | CliError::CommonReferenceStringError(_) => {
+                ExitCode::CommonReferenceStringError
+            }
+            CliError::FilesystemError(err) => err.exit_code(),
+            CliError::NargoError(_) | CliError::ExecutionError(_) => ExitCode::ExecutionFailed,
+            CliError::ProofError(err) => err.exit_code(),
+            CliError::SmartContractError(_)
+            | CliError::ProofSystemCompilerError(_)
+            | CliError::SolcFailed(..)
+            | CliError::EvmFailed(_)
+            | CliError::EvmVerifierRejected(_)
+            | CliError::EvmVerifierAccepted(_) => ExitCode::BackendError,
+        }
+    }
+}
@@ -1,31 +1,81 @@
+mod api;
 mod cli;
 mod constants;
 mod errors;
 mod utils;
-use clap::{Args, Parser, Subcommand};
+use clap::{error::ErrorKind, Args, CommandFactory, Parser, Subcommand};
+use cli::report::Report;
 use color_eyre::eyre;
-use std::path::PathBuf;
+use errors::CliError;
+use halo2_backend::Halo2;
+use std::{path::PathBuf, time::Instant};
 use utils::find_package_root;
 
+pub use acvm::acir::{circuit::Circuit, native_types::WitnessMap};
+pub use api::{common_reference_string, is_trusted_setup, NoOracles, Proof, Prover, Verifier};
+pub use cli::{execute::execute_circuit, oracle::OracleResolver};
+pub use errors::{ExecutionError, ProofError};
+pub use nargo::artifacts::program::PreprocessedProgram;
+pub use noirc_abi::{input_parser::InputValue, InputMap};
+
+/// Runs the command line interface.
+///
+/// On failure the error is printed and the process exits with the code of its class, as listed in
+/// the README, so that scripts can tell an invalid proof apart from broken tooling.
 pub fn start_cli() -> eyre::Result<()> {
     let Halo2Cli {
         command,
-        mut config,
+        config,
+        json,
     } = Halo2Cli::parse();
 
-    config.program_dir = find_package_root(&config.program_dir)?;
+    if let Err(message) = command.validate() {
+        Halo2Cli::command()
+            .error(ErrorKind::ArgumentConflict, message)
+            .exit();
+    }
 
-    match command {
-        Halo2Command::Prove(args) => cli::prove_cmd::run(args, config),
-        Halo2Command::Verify(args) => cli::verify_cmd::run(args, config),
-        Halo2Command::Contract(args) => cli::contract::run(args, config),
-        Halo2Command::Gates(args) => cli::gates_cmd::run(args, config),
-        Halo2Command::ProveAndVerify(args) => cli::prove_and_verify_cmd::run(args, config),
-    }?;
+    let command_name = command.name();
+    let mut report = Report::new(json);
+    let start = Instant::now();
+    let result = run_command(command, config, &mut report);
 
+    if json {
+        report.print_json(command_name, start.elapsed(), result.as_ref().err());
+    }
+
+    if let Err(err) = result {
+        let exit_code = err.exit_code();
+        if !json {
+            eprintln!("Error: {:?}", eyre::Report::from(err));
+        }
+        std::process::exit(exit_code as i32);
+    }
     Ok(())
 }
 
+fn run_command(
+    command: Halo2Command,
+    mut config: Halo2Config,
+    report: &mut Report,
+) -> Result<(), CliError<Halo2>> {
+    if command.needs_package() {
+        config.program_dir = find_package_root(&config.program_dir)?;
+    }
+
+    match command {
+        Halo2Command::Prove(args) => cli::prove_cmd::run(args, config, report),
+        Halo2Command::Verify(args) => cli::verify_cmd::run(args, config, report),
+        Halo2Command::Contract(args) => cli::contract::run(args, config, report),
+        Halo2Command::Gates(args) => cli::gates_cmd::run(args, config, report),
+        Halo2Command::ProveAndVerify(args) => cli::prove_and_verify_cmd::run(args, config, report),
+        Halo2Command::Execute(args) => cli::execute_cmd::run(args, config, report),
+        Halo2Command::ExportVk(args) => cli::export_vk_cmd::run(args, config, report),
+        Halo2Command::Crs(args) => cli::crs_cmd::run(args, report),
+        Halo2Command::Calldata(args) => cli::calldata_cmd::run(args, config, report),
+    }
+}
+
 #[derive(Parser, Debug)]
 #[command(name="halo2-kzg", author, about, long_about = None)]
 struct Halo2Cli {
@@ -34,6 +84,10 @@ struct Halo2Cli {
 
     #[clap(flatten)]
     config: Halo2Config,
+
+    /// Print the result of the command as a single JSON object instead of text
+    #[arg(long, global = true)]
+    json: bool,
 }
 
 #[non_exhaustive]
@@ -51,4 +105,48 @@ enum Halo2Command {
     Verify(cli::verify_cmd::VerifyCommand),
     Gates(cli::gates_cmd::GatesCommand),
     ProveAndVerify(cli::prove_and_verify_cmd::ProveAndVerifyCommand),
+    Execute(cli::execute_cmd::ExecuteCommand),
+    ExportVk(cli::export_vk_cmd::ExportVkCommand),
+    Crs(cli::crs_cmd::CrsCommand),
+    Calldata(cli::calldata_cmd::CalldataCommand),
+}
+
+impl Halo2Command {
+    /// The name of the command as typed on the command line.
+    fn name(&self) -> &'static str {
+        match self {
+            Halo2Command::Contract(_) => "contract",
+            Halo2Command::Prove(_) => "prove",
+            Halo2Command::Verify(_) => "verify",
+            Halo2Command::Gates(_) => "gates",
+            Halo2Command::ProveAndVerify(_) => "prove-and-verify",
+            Halo2Command::Execute(_) => "execute",
+            Halo2Command::ExportVk(_) => "export-vk",
+            Halo2Command::Crs(_) => "crs",
+            Halo2Command::Calldata(_) => "calldata",
+        }
+    }
+
+    /// Checks the combinations of arguments which clap cannot express, returning why they conflict.
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Halo2Command::Prove(args) => args.validate(),
+            Halo2Command::ProveAndVerify(args) => args.validate(),
+            Halo2Command::Verify(args) => args.validate(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the command operates on a Noir package, which is then looked up from `program_dir`.
+    ///
+    /// The common reference string lives in the backend cache, while standalone verification and
+    /// comparing circuit sizes work on explicit files, so those can be used outside of a package.
+    fn needs_package(&self) -> bool {
+        match self {
+            Halo2Command::Crs(_) => false,
+            Halo2Command::Verify(args) => args.needs_package(),
+            Halo2Command::Gates(args) => args.needs_package(),
+            _ => true,
+        }
+    }
 }
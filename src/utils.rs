@@ -1,22 +1,29 @@
 use std::{
     collections::BTreeMap,
     fs::{File, ReadDir},
-    io::Write,
+    io::{ErrorKind, Write},
     path::{Path, PathBuf},
 };
 
-use nargo::{artifacts::program::PreprocessedProgram, manifest::InvalidPackageError};
+use acvm::acir::native_types::WitnessMap;
+use clap::ValueEnum;
+use nargo::artifacts::program::PreprocessedProgram;
 use noirc_abi::{
     input_parser::{Format, InputValue},
     Abi, InputMap, MAIN_RETURN_NAME,
 };
+use sha2::{Digest, Sha256};
 
-use crate::{constants::PROOF_EXT, errors::FilesystemError};
+use crate::{
+    cli::fs::proof::ProofBundle,
+    constants::{PROGRAM_EXT, PROOF_EXT, WITNESS_EXT},
+    errors::FilesystemError,
+};
 
 /// Returns the path of the root directory of the package containing `current_path`.
 ///
-/// Returns a `CliError` if no parent directories of `current_path` contain a manifest file.
-pub(crate) fn find_package_root(current_path: &Path) -> Result<PathBuf, InvalidPackageError> {
+/// Returns a `FilesystemError` if no parent directories of `current_path` contain a manifest file.
+pub(crate) fn find_package_root(current_path: &Path) -> Result<PathBuf, FilesystemError> {
     let manifest_path = find_package_manifest(current_path)?;
 
     let package_root = manifest_path
@@ -28,12 +35,12 @@ pub(crate) fn find_package_root(current_path: &Path) -> Result<PathBuf, InvalidP
 
 /// Returns the path of the manifest file (`Nargo.toml`) of the package containing `current_path`.
 ///
-/// Returns a `CliError` if no parent directories of `current_path` contain a manifest file.
-fn find_package_manifest(current_path: &Path) -> Result<PathBuf, InvalidPackageError> {
-    Ok(current_path
+/// Returns a `FilesystemError` if no parent directories of `current_path` contain a manifest file.
+fn find_package_manifest(current_path: &Path) -> Result<PathBuf, FilesystemError> {
+    current_path
         .ancestors()
         .find_map(|dir| find_file(dir, "Nargo", "toml"))
-        .unwrap())
+        .ok_or_else(|| FilesystemError::MissingManifest(current_path.to_path_buf()))
 }
 
 // Looks for file named `file_name` in path
@@ -58,40 +65,117 @@ fn list_files_and_folders_in<P: AsRef<Path>>(path: P) -> Option<ReadDir> {
     std::fs::read_dir(path).ok()
 }
 
-pub(super) fn create_named_dir(named_dir: &Path, name: &str) -> PathBuf {
+pub(super) fn create_named_dir(named_dir: &Path) -> Result<PathBuf, FilesystemError> {
     std::fs::create_dir_all(named_dir)
-        .unwrap_or_else(|_| panic!("could not create the `{name}` directory"));
+        .map_err(|err| FilesystemError::write_failed(named_dir, err))?;
 
-    PathBuf::from(named_dir)
+    Ok(PathBuf::from(named_dir))
 }
 
-pub(super) fn write_to_file(bytes: &[u8], path: &Path) -> String {
-    let display = path.display();
+pub(super) fn write_to_file(bytes: &[u8], path: &Path) -> Result<String, FilesystemError> {
+    let mut file = File::create(path).map_err(|err| FilesystemError::write_failed(path, err))?;
 
-    let mut file = match File::create(path) {
-        Err(why) => panic!("couldn't create {display}: {why}"),
-        Ok(file) => file,
-    };
+    file.write_all(bytes)
+        .map_err(|err| FilesystemError::write_failed(path, err))?;
 
-    match file.write_all(bytes) {
-        Err(why) => panic!("couldn't write to {display}: {why}"),
-        Ok(_) => display.to_string(),
-    }
+    Ok(path.display().to_string())
+}
+
+/// Writes `bytes` to a temporary file next to `path` and renames it into place, so that
+/// concurrent readers observe either the old or the new contents but never a partial write.
+pub(super) fn write_to_file_atomically(
+    bytes: &[u8],
+    path: &Path,
+) -> Result<String, FilesystemError> {
+    let tmp_path = path.with_extension(format!("tmp-{}", std::process::id()));
+
+    let mut file =
+        File::create(&tmp_path).map_err(|err| FilesystemError::write_failed(&tmp_path, err))?;
+
+    file.write_all(bytes)
+        .and_then(|_| file.sync_all())
+        .map_err(|err| FilesystemError::write_failed(&tmp_path, err))?;
+
+    std::fs::rename(&tmp_path, path).map_err(|err| FilesystemError::write_failed(path, err))?;
+
+    Ok(path.display().to_string())
 }
 
 pub(crate) fn read_program_from_file<P: AsRef<Path>>(
     circuit_path: P,
 ) -> Result<PreprocessedProgram, FilesystemError> {
-    let file_path = circuit_path.as_ref().with_extension("json");
+    let file_path = circuit_path.as_ref().with_extension(PROGRAM_EXT);
 
-    let input_string =
-        std::fs::read(&file_path).map_err(|_| FilesystemError::PathNotValid(file_path))?;
+    let input_string = std::fs::read(&file_path).map_err(|err| match err.kind() {
+        ErrorKind::NotFound => FilesystemError::PathNotValid(file_path.clone()),
+        _ => FilesystemError::read_failed(&file_path, err),
+    })?;
 
-    let program = serde_json::from_slice(&input_string).expect("could not deserialize program");
+    let program = serde_json::from_slice(&input_string)
+        .map_err(|err| FilesystemError::ProgramNotValid(file_path, err))?;
 
     Ok(program)
 }
 
+/// Formats in which circuit inputs can be read and written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum InputFormat {
+    Toml,
+    Json,
+}
+
+impl InputFormat {
+    fn from_ext(ext: &str) -> Option<Self> {
+        match ext {
+            "toml" => Some(InputFormat::Toml),
+            "json" => Some(InputFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+impl From<InputFormat> for Format {
+    fn from(format: InputFormat) -> Self {
+        match format {
+            InputFormat::Toml => Format::Toml,
+            InputFormat::Json => Format::Json,
+        }
+    }
+}
+
+/// Splits the input file name `file_name` into its stem and format.
+///
+/// An explicit `format` takes precedence, then the extension of `file_name`, then whichever of
+/// the TOML and JSON files exists in `path`. Defaults to TOML.
+pub(crate) fn resolve_input_format<P: AsRef<Path>>(
+    path: P,
+    file_name: &str,
+    format: Option<InputFormat>,
+) -> (String, InputFormat) {
+    let (stem, ext_format) = match file_name.rsplit_once('.') {
+        Some((stem, ext)) => match InputFormat::from_ext(ext) {
+            Some(ext_format) => (stem, Some(ext_format)),
+            None => (file_name, None),
+        },
+        None => (file_name, None),
+    };
+
+    let format = format.or(ext_format).unwrap_or_else(|| {
+        [InputFormat::Toml, InputFormat::Json]
+            .into_iter()
+            .find(|format| {
+                let format: Format = (*format).into();
+                path.as_ref()
+                    .join(stem)
+                    .with_extension(format.ext())
+                    .exists()
+            })
+            .unwrap_or(InputFormat::Toml)
+    });
+
+    (stem.to_owned(), format)
+}
+
 /// Returns the circuit's parameters and its return value, if one exists.
 /// # Examples
 ///
@@ -111,13 +195,14 @@ pub(crate) fn read_inputs_from_file<P: AsRef<Path>>(
 
     let file_path = path.as_ref().join(file_name).with_extension(format.ext());
     if !file_path.exists() {
-        return Err(FilesystemError::MissingTomlFile(
+        return Err(FilesystemError::MissingInputFile(
             file_name.to_owned(),
             file_path,
         ));
     }
 
-    let input_string = std::fs::read_to_string(file_path).unwrap();
+    let input_string = std::fs::read_to_string(&file_path)
+        .map_err(|err| FilesystemError::read_failed(&file_path, err))?;
     let mut input_map = format.parse(&input_string, abi)?;
     let return_value = input_map.remove(MAIN_RETURN_NAME);
 
@@ -146,32 +231,48 @@ pub(crate) fn write_inputs_to_file<P: AsRef<Path>>(
         None => format.serialize(input_map)?,
     };
 
-    write_to_file(serialized_output.as_bytes(), &file_path);
+    write_to_file(serialized_output.as_bytes(), &file_path)?;
 
     Ok(())
 }
 
 pub(crate) fn save_proof_to_dir<P: AsRef<Path>>(
-    proof: &[u8],
+    proof_bundle: &ProofBundle,
     proof_name: &str,
     proof_dir: P,
 ) -> Result<PathBuf, FilesystemError> {
-    create_named_dir(proof_dir.as_ref(), "proof");
+    create_named_dir(proof_dir.as_ref())?;
     let proof_path = proof_dir
         .as_ref()
         .join(proof_name)
         .with_extension(PROOF_EXT);
 
-    write_to_file(hex::encode(proof).as_bytes(), &proof_path);
+    let proof_bundle =
+        serde_json::to_vec_pretty(proof_bundle).expect("infallible: serializing to JSON");
+    write_to_file(&proof_bundle, &proof_path)?;
 
     Ok(proof_path)
 }
 
-pub(crate) fn load_hex_data<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, FilesystemError> {
-    let hex_data: Vec<_> = std::fs::read(&path)
-        .map_err(|_| FilesystemError::PathNotValid(path.as_ref().to_path_buf()))?;
+pub(crate) fn save_witness_to_dir<P: AsRef<Path>>(
+    witness: WitnessMap,
+    witness_name: &str,
+    witness_dir: P,
+) -> Result<PathBuf, FilesystemError> {
+    create_named_dir(witness_dir.as_ref())?;
+    let witness_path = witness_dir
+        .as_ref()
+        .join(witness_name)
+        .with_extension(WITNESS_EXT);
 
-    let raw_bytes = hex::decode(hex_data).map_err(FilesystemError::HexArtifactNotValid)?;
+    let buf: Vec<u8> = witness.try_into()?;
+
+    write_to_file(buf.as_slice(), &witness_path)?;
+
+    Ok(witness_path)
+}
 
-    Ok(raw_bytes)
+/// Returns the hex encoded SHA-256 digest of `bytes`.
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
 }